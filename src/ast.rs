@@ -0,0 +1,271 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::bigint::BigInt;
+use crate::span::Span;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Int,
+    Float,
+    /// An arbitrary-precision integer.
+    BigInt,
+    String,
+    Bool,
+    /// An array with elements of the boxed type, written `int[]`.
+    Array(Box<Type>),
+}
+impl Type {
+    /// The type named by a type keyword such as `int`.
+    pub fn from_keyword(word: &str) -> Option<Type> {
+        match word {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "bigint" => Some(Type::BigInt),
+            "string" => Some(Type::String),
+            "bool" => Some(Type::Bool),
+            _ => None,
+        }
+    }
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Type::Int | Type::Float | Type::BigInt)
+    }
+    /// The type two numeric operands are converted to before an arithmetic
+    /// operator or comparison is applied, or `None` if they don't mix. A
+    /// bigint never mixes with a float, since that would lose exactness.
+    pub fn promote(&self, other: &Type) -> Option<Type> {
+        use Type::*;
+        match (self, other) {
+            (Int, Int) => Some(Int),
+            (Int | BigInt, Int | BigInt) => Some(BigInt),
+            (Int | Float, Int | Float) => Some(Float),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::BigInt => write!(f, "bigint"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Array(elem) => write!(f, "{}[]", elem),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// The type of `lhs op rhs`, or `None` if the operator is not defined
+    /// for these operand types. Must agree with `Value::binary`.
+    pub fn result_type(self, lhs: &Type, rhs: &Type) -> Option<Type> {
+        use BinaryOp::*;
+        match (self, lhs, rhs) {
+            // Adding a string to any value concatenates it with the value's
+            // printed form.
+            (Add, Type::String, _) | (Add, _, Type::String) => Some(Type::String),
+            (And | Or, Type::Bool, Type::Bool) => Some(Type::Bool),
+            (Equal | NotEqual, Type::String, Type::String) => Some(Type::Bool),
+            (Equal | NotEqual, Type::Bool, Type::Bool) => Some(Type::Bool),
+            (Add | Sub | Mul | Div | Rem, a, b) => a.promote(b),
+            (Equal | NotEqual | Less | LessEqual | Greater | GreaterEqual, a, b) => {
+                a.promote(b).map(|_| Type::Bool)
+            }
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum UnaryOp {
+    /// `-x`
+    Neg,
+    /// `+x`, which leaves a number unchanged.
+    Plus,
+    /// `!x`
+    Not,
+}
+
+impl UnaryOp {
+    /// The type of `op operand`, or `None` if the operator is not defined
+    /// for the operand type. Must agree with `Value::unary`.
+    pub fn result_type(self, operand: &Type) -> Option<Type> {
+        match (self, operand) {
+            (UnaryOp::Neg | UnaryOp::Plus, t) if t.is_numeric() => Some(t.clone()),
+            (UnaryOp::Not, Type::Bool) => Some(Type::Bool),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Plus => "+",
+            UnaryOp::Not => "!",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    /// For unary and binary expressions this is the span of the operator.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
+    Number(i64),
+    Float(f64),
+    BigInt(BigInt),
+    String(String),
+    Bool(bool),
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// An array literal and the type of its elements.
+    Array(Type, Vec<Expr>),
+    Index(Box<Expr>, Box<Expr>),
+    /// `int(x)`, `float(x)` or `bigint(x)`: converts a number to the type.
+    Cast(Type, Box<Expr>),
+    /// A string literal with `{expr}` interpolations: the printed forms of
+    /// the parts joined together. Text between interpolations is a
+    /// `String` part.
+    Interpolated(Vec<Expr>),
+}
+
+pub type Block = Vec<Stmt>;
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
+    Declare {
+        typ: Type,
+        name: String,
+        value: Expr,
+        /// Text of the `///` comments written before the declaration.
+        doc: Option<String>,
+    },
+    Assign(String, Expr),
+    /// `array[index] = value`
+    SetIndex {
+        array: Expr,
+        index: Expr,
+        value: Expr,
+    },
+    Print {
+        args: Vec<Expr>,
+        newline: bool,
+    },
+    If(Expr, Block, Option<Block>),
+    While(Expr, Block),
+    /// `for (init; condition; step) body`; each part may be left out.
+    For {
+        init: Option<Box<Stmt>>,
+        condition: Option<Expr>,
+        step: Option<Box<Stmt>>,
+        body: Block,
+    },
+    /// `for var in start..end body`, with `end` excluded.
+    ForRange {
+        var: String,
+        start: Expr,
+        end: Expr,
+        body: Block,
+    },
+    Break,
+    Continue,
+    Function(Rc<Function>),
+    Return(Option<Expr>),
+    /// An expression evaluated for its side effects, e.g. a call.
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub typ: Type,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    /// `None` for functions without a `-> type`, which return no value.
+    pub ret: Option<Type>,
+    pub body: Block,
+    pub span: Span,
+    /// Text of the `///` comments written before the function.
+    pub doc: Option<String>,
+}
+
+impl Stmt {
+    /// For a documented declaration, its name as written (`fn add`,
+    /// `int total`) and its doc comment.
+    pub fn doc(&self) -> Option<(String, &str)> {
+        match &self.kind {
+            StmtKind::Declare {
+                typ,
+                name,
+                doc: Some(doc),
+                ..
+            } => Some((format!("{} {}", typ, name), doc)),
+            StmtKind::Function(function) => {
+                Some((format!("fn {}", function.name), function.doc.as_deref()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+}
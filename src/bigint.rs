@@ -0,0 +1,362 @@
+//! Arbitrary-precision integers backing the `bigint` type.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    negative: bool,
+    /// Magnitude in base 2^32, least significant limb first, without
+    /// trailing zero limbs. Zero has no limbs and is never negative.
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    fn new(negative: bool, mut limbs: Vec<u32>) -> BigInt {
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigInt {
+            negative: negative && !limbs.is_empty(),
+            limbs,
+        }
+    }
+
+    /// Parses a non-empty string of ASCII decimal digits.
+    pub fn from_digits(digits: &str) -> BigInt {
+        let mut limbs = Vec::new();
+        for d in digits.bytes() {
+            mul_small_add(&mut limbs, 10, u32::from(d - b'0'));
+        }
+        BigInt::new(false, limbs)
+    }
+
+    /// Parses an optionally signed string of ASCII decimal digits.
+    pub fn parse(s: &str) -> Option<BigInt> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        if digits.is_empty() || !digits.bytes().all(|d| d.is_ascii_digit()) {
+            return None;
+        }
+        let n = BigInt::from_digits(digits);
+        Some(if negative { -&n } else { n })
+    }
+
+    /// The value as an `i64`, or `None` if it is out of range.
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.limbs.len() > 2 {
+            return None;
+        }
+        let m = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0u64, |m, &limb| (m << 32) | u64::from(limb));
+        if self.negative {
+            0i64.checked_sub_unsigned(m)
+        } else {
+            i64::try_from(m).ok()
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    /// Quotient and remainder of division truncating toward zero, so the
+    /// remainder has the sign of `self`. `None` when `other` is zero.
+    pub fn div_rem(&self, other: &BigInt) -> Option<(BigInt, BigInt)> {
+        if other.is_zero() {
+            return None;
+        }
+        let (q, r) = div_rem_mag(&self.limbs, &other.limbs);
+        Some((
+            BigInt::new(self.negative != other.negative, q),
+            BigInt::new(self.negative, r),
+        ))
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> BigInt {
+        let m = n.unsigned_abs();
+        BigInt::new(n < 0, vec![m as u32, (m >> 32) as u32])
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(self.negative, add_mag(&self.limbs, &other.limbs));
+        }
+        match cmp_mag(&self.limbs, &other.limbs) {
+            Ordering::Less => BigInt::new(other.negative, sub_mag(&other.limbs, &self.limbs)),
+            _ => BigInt::new(self.negative, sub_mag(&self.limbs, &other.limbs)),
+        }
+    }
+}
+impl Sub for &BigInt {
+    type Output = BigInt;
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+impl Mul for &BigInt {
+    type Output = BigInt;
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let v = u64::from(a) * u64::from(b) + u64::from(limbs[i + j]) + carry;
+                limbs[i + j] = v as u32;
+                carry = v >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, limbs)
+    }
+}
+impl Neg for &BigInt {
+    type Output = BigInt;
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.limbs.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.limbs, &other.limbs),
+            (true, true) => cmp_mag(&other.limbs, &self.limbs),
+        }
+    }
+}
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // Peel off nine decimal digits at a time, least significant first.
+        let mut limbs = self.limbs.clone();
+        let mut chunks = Vec::new();
+        while !limbs.is_empty() {
+            chunks.push(div_small(&mut limbs, 1_000_000_000));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(mut limbs: Vec<u32>) -> Vec<u32> {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    limbs
+}
+
+fn cmp_mag(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let v = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        limbs.push(v as u32);
+        carry = v >> 32;
+    }
+    limbs.push(carry as u32);
+    trim(limbs)
+}
+
+/// `a - b` for magnitudes with `a >= b`.
+fn sub_mag(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut limbs = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &x) in a.iter().enumerate() {
+        let mut v = i64::from(x) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+        borrow = 0;
+        if v < 0 {
+            v += 1 << 32;
+            borrow = 1;
+        }
+        limbs.push(v as u32);
+    }
+    trim(limbs)
+}
+
+/// Long division one bit at a time; slow for huge operands but simple.
+fn div_rem_mag(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut rem: Vec<u32> = Vec::new();
+    for bit in (0..a.len() * 32).rev() {
+        mul_small_add(&mut rem, 2, (a[bit / 32] >> (bit % 32)) & 1);
+        if cmp_mag(&rem, b) != Ordering::Less {
+            rem = sub_mag(&rem, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (trim(quotient), rem)
+}
+
+/// `limbs = limbs * m + a`.
+fn mul_small_add(limbs: &mut Vec<u32>, m: u32, a: u32) {
+    let mut carry = u64::from(a);
+    for limb in limbs.iter_mut() {
+        let v = u64::from(*limb) * u64::from(m) + carry;
+        *limb = v as u32;
+        carry = v >> 32;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+/// Divides `limbs` in place by `d`, returning the remainder.
+fn div_small(limbs: &mut Vec<u32>, d: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in limbs.iter_mut().rev() {
+        let v = (rem << 32) | u64::from(*limb);
+        *limb = (v / u64::from(d)) as u32;
+        rem = v % u64::from(d);
+    }
+    *limbs = trim(std::mem::take(limbs));
+    rem as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        BigInt::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_across_limb_boundaries() {
+        for s in [
+            "0",
+            "4294967295",
+            "4294967296",
+            "18446744073709551615",
+            "18446744073709551616",
+            "-18446744073709551616",
+            "1000000000",
+            "1000000000000000001",
+            "-123456789012345678901234567890",
+        ] {
+            assert_eq!(big(s).to_string(), s);
+        }
+        assert_eq!(big("+42").to_string(), "42");
+        assert_eq!(big("0007").to_string(), "7");
+        assert_eq!(BigInt::parse(""), None);
+        assert_eq!(BigInt::parse("-"), None);
+        assert_eq!(BigInt::parse("12a"), None);
+    }
+
+    #[test]
+    fn negative_zero_is_zero() {
+        assert_eq!(big("-0"), BigInt::from(0));
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(-&BigInt::from(0), BigInt::from(0));
+        assert_eq!(&big("-5") + &big("5"), BigInt::from(0));
+        assert!(!(&big("-3") * &BigInt::from(0)).is_negative());
+    }
+
+    #[test]
+    fn adds_and_subtracts_with_carries() {
+        let max32 = big("4294967295");
+        assert_eq!((&max32 + &BigInt::from(1)).to_string(), "4294967296");
+        let max64 = big("18446744073709551615");
+        assert_eq!(
+            (&max64 + &BigInt::from(1)).to_string(),
+            "18446744073709551616"
+        );
+        assert_eq!((&big("18446744073709551616") - &BigInt::from(1)), max64);
+        assert_eq!((&BigInt::from(3) - &BigInt::from(5)).to_string(), "-2");
+        assert_eq!((&BigInt::from(-3) + &BigInt::from(5)).to_string(), "2");
+    }
+
+    #[test]
+    fn multiplies() {
+        let max64 = big("18446744073709551615");
+        assert_eq!(
+            (&max64 * &max64).to_string(),
+            "340282366920938463426481119284349108225"
+        );
+        assert_eq!((&BigInt::from(-4) * &BigInt::from(6)).to_string(), "-24");
+        assert_eq!((&BigInt::from(-4) * &BigInt::from(-6)).to_string(), "24");
+    }
+
+    #[test]
+    fn divides_truncating_toward_zero() {
+        let cases = [
+            (7, 2, 3, 1),
+            (-7, 2, -3, -1),
+            (7, -2, -3, 1),
+            (-7, -2, 3, -1),
+            (1, 5, 0, 1),
+        ];
+        for (a, b, q, r) in cases {
+            let (quotient, rem) = BigInt::from(a).div_rem(&BigInt::from(b)).unwrap();
+            assert_eq!((quotient, rem), (BigInt::from(q), BigInt::from(r)));
+        }
+        assert_eq!(BigInt::from(1).div_rem(&BigInt::from(0)), None);
+
+        let a = big("340282366920938463463374607431768211457");
+        let b = big("18446744073709551616");
+        let (q, r) = a.div_rem(&b).unwrap();
+        assert_eq!(q, b);
+        assert_eq!(r, BigInt::from(1));
+        let (q, r) = big("-18446744073709551617")
+            .div_rem(&big("4294967296"))
+            .unwrap();
+        assert_eq!(q.to_string(), "-4294967296");
+        assert_eq!(r.to_string(), "-1");
+    }
+
+    #[test]
+    fn converts_to_i64_at_its_limits() {
+        assert_eq!(big("9223372036854775807").to_i64(), Some(i64::MAX));
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775808").to_i64(), Some(i64::MIN));
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+        assert_eq!(big("18446744073709551616").to_i64(), None);
+        assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+        assert_eq!(BigInt::from(0).to_i64(), Some(0));
+    }
+
+    #[test]
+    fn orders_by_sign_then_magnitude() {
+        assert!(big("-18446744073709551616") < BigInt::from(-1));
+        assert!(BigInt::from(-1) < BigInt::from(0));
+        assert!(big("4294967295") < big("4294967296"));
+    }
+}
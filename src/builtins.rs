@@ -0,0 +1,505 @@
+//! Functions built into the language. They are called like user-defined
+//! functions but may accept arguments of several types.
+//!
+//! String functions work on unicode characters rather than bytes: lengths,
+//! positions and substring bounds all count characters.
+//!
+//! Math functions that work in floating point (`sqrt`, `floor`, the trig
+//! functions, `pow` with a float, ...) accept ints too. Where their result
+//! is undefined, such as `sqrt(-1.0)` or `asin(2.0)`, they fail with a
+//! domain error (E0312) rather than returning NaN; a NaN argument still
+//! gives a NaN result. Integer results that overflow are E0309 errors, as
+//! for the arithmetic operators.
+//!
+//! The input functions read lines from stdin, printing their prompt first.
+//! `input_int` and `input_bool` ask again after a line that isn't a valid
+//! value. Reading past the end of stdin is an error (E0313); `eof` tells
+//! whether any input is left.
+//!
+//! The random functions share one generator per run. It is seeded from
+//! `--seed` on the command line or by calling `seed`, and otherwise
+//! differently on every run.
+
+use std::io::{self, BufRead, Read, Write};
+
+use crate::ast::{BinaryOp, Type};
+use crate::bigint::BigInt;
+use crate::error::{Diagnostic, Result};
+use crate::random::Rng;
+use crate::span::Span;
+use crate::value::Value;
+
+pub fn is_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "len"
+            | "push"
+            | "pop"
+            | "substring"
+            | "contains"
+            | "index_of"
+            | "starts_with"
+            | "ends_with"
+            | "split"
+            | "join"
+            | "trim"
+            | "to_upper"
+            | "to_lower"
+            | "replace"
+            | "chars"
+            | "to_string"
+            | "parse_int"
+            | "to_bool"
+            | "abs"
+            | "min"
+            | "max"
+            | "clamp"
+            | "pow"
+            | "gcd"
+            | "lcm"
+            | "random_int"
+            | "random_float"
+            | "shuffle"
+            | "seed"
+            | "input"
+            | "input_int"
+            | "input_bool"
+            | "input_all"
+            | "eof"
+    ) || float_function(name).is_some()
+        || name == "atan2"
+}
+
+pub fn usage(name: &str) -> &'static str {
+    match name {
+        "len" => "len(array) -> int or len(string) -> int",
+        "push" => "push(array, value)",
+        "pop" => "pop(array) -> element",
+        "substring" => "substring(string, start: int, end: int) -> string",
+        "contains" => "contains(string, part: string) -> bool",
+        "index_of" => "index_of(string, part: string) -> int",
+        "starts_with" => "starts_with(string, prefix: string) -> bool",
+        "ends_with" => "ends_with(string, suffix: string) -> bool",
+        "split" => "split(string, separator: string) -> string[]",
+        "join" => "join(string[], separator: string) -> string",
+        "trim" => "trim(string) -> string",
+        "to_upper" => "to_upper(string) -> string",
+        "to_lower" => "to_lower(string) -> string",
+        "replace" => "replace(string, from: string, to: string) -> string",
+        "chars" => "chars(string) -> string[]",
+        "to_string" => "to_string(value) -> string",
+        "parse_int" => "parse_int(string) -> int or parse_int(string, default: int) -> int",
+        "to_bool" => "to_bool(string) -> bool or to_bool(int) -> bool",
+        "abs" => "abs(number) -> number",
+        "min" => "min(number, number) -> number",
+        "max" => "max(number, number) -> number",
+        "clamp" => "clamp(number, low: number, high: number) -> number",
+        "pow" => "pow(int, exponent: int) -> int, pow(bigint, exponent: int) -> bigint or pow(float, exponent: float) -> float",
+        "gcd" => "gcd(int, int) -> int",
+        "lcm" => "lcm(int, int) -> int",
+        "sqrt" => "sqrt(float) -> float",
+        "floor" => "floor(float) -> float",
+        "ceil" => "ceil(float) -> float",
+        "round" => "round(float) -> float",
+        "sin" => "sin(float) -> float",
+        "cos" => "cos(float) -> float",
+        "tan" => "tan(float) -> float",
+        "asin" => "asin(float) -> float",
+        "acos" => "acos(float) -> float",
+        "atan" => "atan(float) -> float",
+        "atan2" => "atan2(y: float, x: float) -> float",
+        "random_int" => "random_int(low: int, high: int) -> int",
+        "random_float" => "random_float() -> float",
+        "shuffle" => "shuffle(array)",
+        "seed" => "seed(int)",
+        "input" => "input(prompt: string) -> string",
+        "input_int" => "input_int(prompt: string) -> int",
+        "input_bool" => "input_bool(prompt: string) -> bool",
+        "input_all" => "input_all() -> string",
+        "eof" => "eof() -> bool",
+        _ => unreachable!("{} is not a built-in function", name),
+    }
+}
+
+/// The type returned by calling `name` with arguments of types `args`
+/// (`None` if it returns no value), or the usage message if the arguments
+/// don't fit.
+pub fn result_type(name: &str, args: &[Type]) -> std::result::Result<Option<Type>, String> {
+    match (name, args) {
+        ("len", [Type::Array(_)]) => Ok(Some(Type::Int)),
+        ("push", [Type::Array(elem), value]) if **elem == *value => Ok(None),
+        ("pop", [Type::Array(elem)]) => Ok(Some(*elem.clone())),
+        ("len", [Type::String]) => Ok(Some(Type::Int)),
+        ("substring", [Type::String, Type::Int, Type::Int]) => Ok(Some(Type::String)),
+        ("contains" | "starts_with" | "ends_with", [Type::String, Type::String]) => {
+            Ok(Some(Type::Bool))
+        }
+        ("index_of", [Type::String, Type::String]) => Ok(Some(Type::Int)),
+        ("split", [Type::String, Type::String]) | ("chars", [Type::String]) => {
+            Ok(Some(Type::Array(Box::new(Type::String))))
+        }
+        ("join", [Type::Array(elem), Type::String]) if **elem == Type::String => {
+            Ok(Some(Type::String))
+        }
+        ("trim" | "to_upper" | "to_lower", [Type::String])
+        | ("replace", [Type::String, Type::String, Type::String]) => Ok(Some(Type::String)),
+        ("to_string", [_]) => Ok(Some(Type::String)),
+        ("parse_int", [Type::String] | [Type::String, Type::Int]) => Ok(Some(Type::Int)),
+        ("to_bool", [Type::String | Type::Int | Type::Bool]) => Ok(Some(Type::Bool)),
+        ("abs", [t]) if t.is_numeric() => Ok(Some(t.clone())),
+        ("min" | "max", [a, b]) if a.promote(b).is_some() => Ok(a.promote(b)),
+        ("clamp", [x, low, high]) if x.promote(low).and_then(|t| t.promote(high)).is_some() => {
+            Ok(x.promote(low).and_then(|t| t.promote(high)))
+        }
+        ("pow", [base @ (Type::Int | Type::BigInt), Type::Int]) => Ok(Some(base.clone())),
+        ("pow" | "atan2", [a, b]) if a.promote(b).is_some_and(|t| t != Type::BigInt) => {
+            Ok(Some(Type::Float))
+        }
+        ("gcd" | "lcm", [Type::Int, Type::Int]) => Ok(Some(Type::Int)),
+        (_, [Type::Int | Type::Float]) if float_function(name).is_some() => Ok(Some(Type::Float)),
+        ("random_int", [Type::Int, Type::Int]) => Ok(Some(Type::Int)),
+        ("random_float", []) => Ok(Some(Type::Float)),
+        ("shuffle", [Type::Array(_)]) | ("seed", [Type::Int]) => Ok(None),
+        ("input", [] | [Type::String]) | ("input_all", []) => Ok(Some(Type::String)),
+        ("input_int", [] | [Type::String]) => Ok(Some(Type::Int)),
+        ("input_bool", [] | [Type::String]) | ("eof", []) => Ok(Some(Type::Bool)),
+        _ => Err(format!("expected {}", usage(name))),
+    }
+}
+
+/// Calls `name`, drawing random numbers from `rng`. The arguments must have
+/// been accepted by [`result_type`].
+pub fn call(name: &str, args: Vec<Value>, rng: &mut Rng, span: Span) -> Result<Option<Value>> {
+    match (name, args.as_slice()) {
+        ("len", [Value::Array(array)]) => Ok(Some(Value::Int(array.items.borrow().len() as i64))),
+        ("push", [Value::Array(array), value]) if value.typ() == array.elem => {
+            array.items.borrow_mut().push(value.clone());
+            Ok(None)
+        }
+        ("pop", [Value::Array(array)]) => match array.items.borrow_mut().pop() {
+            Some(value) => Ok(Some(value)),
+            None => Err(Diagnostic::runtime(
+                "E0308",
+                span,
+                "pop from an empty array",
+            )),
+        },
+        ("len", [Value::String(s)]) => Ok(Some(Value::Int(s.chars().count() as i64))),
+        ("substring", [Value::String(s), Value::Int(start), Value::Int(end)]) => {
+            let chars: Vec<char> = s.chars().collect();
+            if *start < 0 || start > end || *end > chars.len() as i64 {
+                return Err(Diagnostic::runtime(
+                    "E0307",
+                    span,
+                    format!(
+                        "substring {}..{} is out of bounds for a string of length {}",
+                        start,
+                        end,
+                        chars.len()
+                    ),
+                ));
+            }
+            let sub = chars[*start as usize..*end as usize].iter().collect();
+            Ok(Some(Value::String(sub)))
+        }
+        ("contains", [Value::String(s), Value::String(part)]) => {
+            Ok(Some(Value::Bool(s.contains(part.as_str()))))
+        }
+        ("index_of", [Value::String(s), Value::String(part)]) => {
+            let index = match s.find(part.as_str()) {
+                Some(byte) => s[..byte].chars().count() as i64,
+                None => -1,
+            };
+            Ok(Some(Value::Int(index)))
+        }
+        ("starts_with", [Value::String(s), Value::String(prefix)]) => {
+            Ok(Some(Value::Bool(s.starts_with(prefix.as_str()))))
+        }
+        ("ends_with", [Value::String(s), Value::String(suffix)]) => {
+            Ok(Some(Value::Bool(s.ends_with(suffix.as_str()))))
+        }
+        // An empty separator splits the string into its characters.
+        ("split", [Value::String(s), Value::String(sep)]) if sep.is_empty() => Ok(Some(chars(s))),
+        ("split", [Value::String(s), Value::String(sep)]) => {
+            Ok(Some(strings(s.split(sep.as_str()))))
+        }
+        ("join", [Value::Array(array), Value::String(sep)]) => {
+            let items = array.items.borrow();
+            let parts: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+            Ok(Some(Value::String(parts.join(sep))))
+        }
+        ("trim", [Value::String(s)]) => Ok(Some(Value::String(s.trim().to_string()))),
+        ("to_upper", [Value::String(s)]) => Ok(Some(Value::String(s.to_uppercase()))),
+        ("to_lower", [Value::String(s)]) => Ok(Some(Value::String(s.to_lowercase()))),
+        ("replace", [Value::String(s), Value::String(from), Value::String(to)]) => {
+            Ok(Some(Value::String(s.replace(from.as_str(), to))))
+        }
+        ("chars", [Value::String(s)]) => Ok(Some(chars(s))),
+        ("to_string", [value]) => Ok(Some(Value::String(value.to_string()))),
+        ("parse_int", [Value::String(s), fallback @ ..]) => match (s.trim().parse(), fallback) {
+            (Ok(n), _) => Ok(Some(Value::Int(n))),
+            (Err(_), [default]) => Ok(Some(default.clone())),
+            (Err(_), _) => Err(invalid_conversion(s, "int", span)),
+        },
+        ("to_bool", [Value::String(s)]) => match s.trim() {
+            "true" => Ok(Some(Value::Bool(true))),
+            "false" => Ok(Some(Value::Bool(false))),
+            _ => Err(invalid_conversion(s, "bool", span)),
+        },
+        ("to_bool", [Value::Int(n)]) => Ok(Some(Value::Bool(*n != 0))),
+        ("to_bool", [Value::Bool(b)]) => Ok(Some(Value::Bool(*b))),
+        ("abs", [Value::Int(n)]) => n
+            .checked_abs()
+            .map(|n| Some(Value::Int(n)))
+            .ok_or_else(|| overflow(name, &args, span)),
+        ("abs", [Value::Float(x)]) => Ok(Some(Value::Float(x.abs()))),
+        ("abs", [Value::BigInt(n)]) if n.is_negative() => Ok(Some(Value::BigInt(-n))),
+        ("abs", [n @ Value::BigInt(_)]) => Ok(Some(n.clone())),
+        ("min" | "max" | "clamp", _) => {
+            let typ = args
+                .iter()
+                .skip(1)
+                .try_fold(args[0].typ(), |t, arg| t.promote(&arg.typ()))
+                .unwrap();
+            let values: Vec<Value> = args.iter().map(|v| v.cast(&typ).unwrap()).collect();
+            match (name, values.as_slice()) {
+                ("min", [a, b]) => Ok(Some(if less(b, a) { b } else { a }.clone())),
+                ("max", [a, b]) => Ok(Some(if less(a, b) { b } else { a }.clone())),
+                (_, [_, low, high]) if less(high, low) => Err(domain_error(
+                    format!("clamp range {:?} to {:?} is empty", low, high),
+                    span,
+                )),
+                (_, [x, low, _]) if less(x, low) => Ok(Some(low.clone())),
+                (_, [x, _, high]) if less(high, x) => Ok(Some(high.clone())),
+                (_, [x, _, _]) => Ok(Some(x.clone())),
+                _ => unreachable!(),
+            }
+        }
+        ("pow", [Value::Int(_) | Value::BigInt(_), Value::Int(exp)]) if *exp < 0 => {
+            Err(domain_error(
+                format!(
+                    "negative exponent {} in an integer pow, use a float base instead",
+                    exp
+                ),
+                span,
+            ))
+        }
+        ("pow", [Value::Int(base), Value::Int(exp)]) => int_pow(*base, *exp)
+            .map(|n| Some(Value::Int(n)))
+            .ok_or_else(|| overflow(name, &args, span)),
+        ("pow", [Value::BigInt(base), Value::Int(exp)]) => {
+            Ok(Some(Value::BigInt(big_pow(base, *exp))))
+        }
+        ("pow", [a, b]) => float_result(name, &args, float(a).powf(float(b)), span),
+        ("atan2", [y, x]) => float_result(name, &args, float(y).atan2(float(x)), span),
+        ("gcd", [Value::Int(a), Value::Int(b)]) => i64::try_from(gcd(*a, *b))
+            .map(|n| Some(Value::Int(n)))
+            .map_err(|_| overflow(name, &args, span)),
+        ("lcm", [Value::Int(a), Value::Int(b)]) => {
+            let lcm = match gcd(*a, *b) {
+                0 => Some(0),
+                d => (a.unsigned_abs() / d)
+                    .checked_mul(b.unsigned_abs())
+                    .and_then(|n| i64::try_from(n).ok()),
+            };
+            lcm.map(|n| Some(Value::Int(n)))
+                .ok_or_else(|| overflow(name, &args, span))
+        }
+        ("random_int", [Value::Int(low), Value::Int(high)]) if low > high => Err(domain_error(
+            format!("random_int range {} to {} is empty", low, high),
+            span,
+        )),
+        ("random_int", [Value::Int(low), Value::Int(high)]) => {
+            Ok(Some(Value::Int(rng.int(*low, *high))))
+        }
+        ("random_float", []) => Ok(Some(Value::Float(rng.float()))),
+        // Fisher-Yates, so every order is equally likely.
+        ("shuffle", [Value::Array(array)]) => {
+            let mut items = array.items.borrow_mut();
+            for i in (1..items.len()).rev() {
+                items.swap(i, rng.below(i as u64 + 1) as usize);
+            }
+            Ok(None)
+        }
+        ("seed", [Value::Int(n)]) => {
+            *rng = Rng::new(*n as u64);
+            Ok(None)
+        }
+        ("input", prompt) => Ok(Some(Value::String(read_line(prompt, span)?))),
+        ("input_int", prompt) => loop {
+            let line = read_line(prompt, span)?;
+            match line.trim().parse() {
+                Ok(n) => return Ok(Some(Value::Int(n))),
+                Err(_) => eprintln!("expected an int, found {:?}; try again", line),
+            }
+        },
+        ("input_bool", prompt) => loop {
+            let line = read_line(prompt, span)?;
+            match line.trim().to_lowercase().as_str() {
+                "true" | "yes" | "y" => return Ok(Some(Value::Bool(true))),
+                "false" | "no" | "n" => return Ok(Some(Value::Bool(false))),
+                _ => eprintln!("expected true or false, found {:?}; try again", line),
+            }
+        },
+        ("input_all", []) => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| read_error(e, span))?;
+            Ok(Some(Value::String(input)))
+        }
+        ("eof", []) => {
+            let mut stdin = io::stdin().lock();
+            let buffer = stdin.fill_buf().map_err(|e| read_error(e, span))?;
+            Ok(Some(Value::Bool(buffer.is_empty())))
+        }
+        (_, [x]) if float_function(name).is_some() => {
+            float_result(name, &args, float_function(name).unwrap()(float(x)), span)
+        }
+        _ => unreachable!("checked call to {}: expected {}", name, usage(name)),
+    }
+}
+
+fn invalid_conversion(s: &str, typ: &str, span: Span) -> Diagnostic {
+    Diagnostic::runtime("E0311", span, format!("cannot convert {:?} to {}", s, typ))
+}
+
+/// A `string[]` of the unicode characters of `s`, each as a string.
+fn chars(s: &str) -> Value {
+    strings(s.chars().map(String::from))
+}
+
+fn strings(items: impl Iterator<Item = impl Into<String>>) -> Value {
+    Value::array(
+        Type::String,
+        items.map(|s| Value::String(s.into())).collect(),
+    )
+}
+
+/// Prints the `prompt` argument of an input function, if any, and reads a
+/// line from stdin without its line ending.
+fn read_line(prompt: &[Value], span: Span) -> Result<String> {
+    if let [prompt] = prompt {
+        print!("{}", prompt);
+        io::stdout().flush().map_err(|e| read_error(e, span))?;
+    }
+    let mut line = String::new();
+    if io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| read_error(e, span))?
+        == 0
+    {
+        return Err(Diagnostic::runtime(
+            "E0313",
+            span,
+            "unexpected end of input",
+        ));
+    }
+    let line = line.strip_suffix('\n').unwrap_or(&line);
+    Ok(line.strip_suffix('\r').unwrap_or(line).to_string())
+}
+
+fn read_error(e: io::Error, span: Span) -> Diagnostic {
+    Diagnostic::runtime("E0303", span, format!("failed to read input: {}", e))
+}
+
+/// The float functions of one argument.
+fn float_function(name: &str) -> Option<fn(f64) -> f64> {
+    Some(match name {
+        "sqrt" => f64::sqrt,
+        "floor" => f64::floor,
+        "ceil" => f64::ceil,
+        // Halfway cases round away from zero.
+        "round" => f64::round,
+        "sin" => f64::sin,
+        "cos" => f64::cos,
+        "tan" => f64::tan,
+        "asin" => f64::asin,
+        "acos" => f64::acos,
+        "atan" => f64::atan,
+        _ => return None,
+    })
+}
+
+/// The value of an int or float argument as a float.
+fn float(value: &Value) -> f64 {
+    match value {
+        Value::Int(n) => *n as f64,
+        Value::Float(x) => *x,
+        _ => unreachable!("{:?} is not an int or float", value),
+    }
+}
+
+/// The result `x` of a float math function, which is a domain error if it
+/// is NaN although none of the arguments were.
+fn float_result(name: &str, args: &[Value], x: f64, span: Span) -> Result<Option<Value>> {
+    if x.is_nan() && !args.iter().any(|arg| float(arg).is_nan()) {
+        return Err(domain_error(
+            format!("{} is undefined for {}", name, arguments(args)),
+            span,
+        ));
+    }
+    Ok(Some(Value::Float(x)))
+}
+
+fn less(a: &Value, b: &Value) -> bool {
+    a.binary(BinaryOp::Less, b) == Ok(Value::Bool(true))
+}
+
+/// `base` to the power `exp`, or `None` if it overflows.
+fn int_pow(mut base: i64, mut exp: i64) -> Option<i64> {
+    let mut result = 1i64;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result.checked_mul(base)?;
+        }
+        exp >>= 1;
+        // Any square still to be multiplied in must not overflow.
+        if exp > 0 {
+            base = base.checked_mul(base)?;
+        }
+    }
+    Some(result)
+}
+
+fn big_pow(base: &BigInt, mut exp: i64) -> BigInt {
+    let mut result = BigInt::from(1);
+    let mut base = base.clone();
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = &result * &base;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = &base * &base;
+        }
+    }
+    result
+}
+
+/// The greatest common divisor of `a` and `b`. It is out of range for an
+/// int only for `gcd(i64::MIN, 0)` and `gcd(i64::MIN, i64::MIN)`.
+fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn arguments(args: &[Value]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| format!("{:?}", arg)).collect();
+    args.join(", ")
+}
+
+fn overflow(name: &str, args: &[Value], span: Span) -> Diagnostic {
+    Diagnostic::runtime(
+        "E0309",
+        span,
+        format!("integer overflow in {}({})", name, arguments(args)),
+    )
+}
+
+fn domain_error(message: String, span: Span) -> Diagnostic {
+    Diagnostic::runtime("E0312", span, message)
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use crate::ast::{Block, Expr, ExprKind, Function, Stmt, StmtKind, Type};
+use crate::builtins;
+use crate::error::Diagnostic;
+use crate::span::Span;
+
+/// Static type checking pass run over the whole program before execution.
+/// All type errors are collected rather than stopping at the first.
+pub struct Checker<'a> {
+    scopes: Vec<HashMap<&'a str, Type>>,
+    functions: HashMap<&'a str, &'a Function>,
+    /// Return type of the function being checked, `None` at the top level.
+    returns: Option<Option<Type>>,
+    errors: Vec<Diagnostic>,
+}
+impl<'a> Checker<'a> {
+    pub fn new() -> Self {
+        Checker {
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            returns: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check(mut self, program: &'a [Stmt]) -> Result<(), Vec<Diagnostic>> {
+        for stmt in program {
+            if let StmtKind::Function(function) = &stmt.kind {
+                if self.functions.contains_key(function.name.as_str()) {
+                    self.errors.push(Diagnostic::typ(
+                        "E0210",
+                        function.span,
+                        format!("function {} is already declared", function.name),
+                    ));
+                } else {
+                    self.functions.insert(&function.name, function);
+                }
+            }
+        }
+        self.statements(program);
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+    }
+    /// Declares `name` in the innermost scope. It may shadow a variable of an
+    /// enclosing block but not one declared in the same block.
+    fn declare(&mut self, name: &'a str, typ: Type, span: Span) {
+        if self.scopes.last_mut().unwrap().insert(name, typ).is_some() {
+            self.errors.push(Diagnostic::typ(
+                "E0210",
+                span,
+                format!("{} is already declared in this scope", name),
+            ));
+        }
+    }
+
+    fn block(&mut self, block: &'a Block) {
+        self.scopes.push(HashMap::new());
+        self.statements(block);
+        self.scopes.pop();
+    }
+    fn statements(&mut self, stmts: &'a [Stmt]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &'a Stmt) {
+        match &stmt.kind {
+            StmtKind::Declare {
+                typ,
+                name: id,
+                value,
+                ..
+            } => {
+                if let Some(found) = self.expr(value) {
+                    if found != *typ {
+                        self.mismatch(value, typ, found, &format!("{} {}", typ, id));
+                    }
+                }
+                self.declare(id, typ.clone(), stmt.span);
+            }
+            StmtKind::Assign(id, value) => {
+                if let (Some(expected), Some(found)) = (self.lookup(id), self.expr(value)) {
+                    if found != expected {
+                        self.mismatch(value, &expected, found, id);
+                    }
+                }
+            }
+            StmtKind::SetIndex {
+                array,
+                index,
+                value,
+            } => {
+                let elem = self.index(array, index);
+                if let (Some(expected), Some(found)) = (elem, self.expr(value)) {
+                    if found != expected {
+                        self.mismatch(value, &expected, found, "array element");
+                    }
+                }
+            }
+            StmtKind::Print { args, .. } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+            }
+            StmtKind::If(condition, then_block, else_block) => {
+                self.condition(condition);
+                self.block(then_block);
+                if let Some(else_block) = else_block {
+                    self.block(else_block);
+                }
+            }
+            StmtKind::While(condition, body) => {
+                self.condition(condition);
+                self.block(body);
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                if let Some(init) = init {
+                    self.statement(init);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(step) = step {
+                    self.statement(step);
+                }
+                self.block(body);
+                self.scopes.pop();
+            }
+            StmtKind::ForRange {
+                var,
+                start,
+                end,
+                body,
+            } => {
+                for bound in [start, end] {
+                    if let Some(found) = self.expr(bound) {
+                        if found != Type::Int {
+                            self.error(
+                                "E0209",
+                                bound,
+                                format!("range bounds must be int, found {}", found),
+                            );
+                        }
+                    }
+                }
+                self.scopes.push(HashMap::new());
+                self.declare(var, Type::Int, stmt.span);
+                self.block(body);
+                self.scopes.pop();
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+            StmtKind::Function(function) => self.function(function),
+            StmtKind::Return(value) => {
+                let Some(ret) = self.returns.clone() else {
+                    return;
+                };
+                match (ret, value) {
+                    (Some(expected), Some(value)) => {
+                        if let Some(found) = self.expr(value) {
+                            if found != expected {
+                                self.error(
+                                    "E0206",
+                                    value,
+                                    format!(
+                                        "expected return value of type {}, found {}",
+                                        expected, found
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    (None, Some(value)) => self.error(
+                        "E0206",
+                        value,
+                        "function without a return type cannot return a value".to_string(),
+                    ),
+                    (Some(expected), None) => self.errors.push(Diagnostic::typ(
+                        "E0206",
+                        stmt.span,
+                        format!("expected return value of type {}", expected),
+                    )),
+                    (None, None) => {}
+                }
+            }
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::Call(name, args) => {
+                    self.call(expr, name, args);
+                }
+                _ => {
+                    self.expr(expr);
+                }
+            },
+        }
+    }
+
+    fn function(&mut self, function: &'a Function) {
+        self.scopes.push(HashMap::new());
+        for param in &function.params {
+            self.declare(&param.name, param.typ.clone(), function.span);
+        }
+        self.returns = Some(function.ret.clone());
+        self.block(&function.body);
+        self.returns = None;
+        self.scopes.pop();
+
+        if let Some(ret) = &function.ret {
+            if !always_returns(&function.body) {
+                self.errors.push(Diagnostic::typ(
+                    "E0208",
+                    function.span,
+                    format!(
+                        "{} may end without returning a value of type {}",
+                        function.name, ret
+                    ),
+                ));
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &'a Expr) {
+        if let Some(found) = self.expr(condition) {
+            if found != Type::Bool {
+                self.error(
+                    "E0203",
+                    condition,
+                    format!("condition must be bool, found {}", found),
+                );
+            }
+        }
+    }
+
+    /// The type of `expr`, or `None` if it contains a type error (already
+    /// reported) or produces no value.
+    fn expr(&mut self, expr: &'a Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Number(_) => Some(Type::Int),
+            ExprKind::Float(_) => Some(Type::Float),
+            ExprKind::BigInt(_) => Some(Type::BigInt),
+            ExprKind::String(_) => Some(Type::String),
+            ExprKind::Bool(_) => Some(Type::Bool),
+            ExprKind::Variable(id) => self.lookup(id),
+            ExprKind::Array(elem, items) => {
+                for item in items {
+                    if let Some(found) = self.expr(item) {
+                        if found != *elem {
+                            self.mismatch(item, elem, found, "array element");
+                        }
+                    }
+                }
+                Some(Type::Array(Box::new(elem.clone())))
+            }
+            ExprKind::Index(array, index) => self.index(array, index),
+            ExprKind::Unary(op, operand) => {
+                let found = self.expr(operand)?;
+                let typ = op.result_type(&found);
+                if typ.is_none() {
+                    self.error(
+                        "E0202",
+                        expr,
+                        format!("invalid operand for {}: {}", op, found),
+                    );
+                }
+                typ
+            }
+            ExprKind::Binary(lhs, op, rhs) => {
+                let (a, b) = (self.expr(lhs)?, self.expr(rhs)?);
+                let typ = op.result_type(&a, &b);
+                if typ.is_none() {
+                    self.error(
+                        "E0202",
+                        expr,
+                        format!("invalid operands for {}: {} and {}", op, a, b),
+                    );
+                }
+                typ
+            }
+            ExprKind::Call(name, args) => {
+                let ret = self.call(expr, name, args)?;
+                if ret.is_none() {
+                    self.error("E0207", expr, format!("{} does not return a value", name));
+                }
+                ret
+            }
+            ExprKind::Cast(typ, operand) => {
+                let found = self.expr(operand)?;
+                if !found.is_numeric() {
+                    let hint = match found {
+                        Type::String => ", use parse_int to read an int from a string",
+                        _ => "",
+                    };
+                    self.error(
+                        "E0202",
+                        operand,
+                        format!("cannot convert {} to {}{}", found, typ, hint),
+                    );
+                }
+                Some(typ.clone())
+            }
+            ExprKind::Interpolated(parts) => {
+                for part in parts {
+                    self.expr(part);
+                }
+                Some(Type::String)
+            }
+        }
+    }
+
+    /// The element type of `array[index]`, reporting a non-array operand or
+    /// a non-int index.
+    fn index(&mut self, array: &'a Expr, index: &'a Expr) -> Option<Type> {
+        if let Some(found) = self.expr(index) {
+            if found != Type::Int {
+                self.error(
+                    "E0209",
+                    index,
+                    format!("array index must be int, found {}", found),
+                );
+            }
+        }
+        match self.expr(array)? {
+            Type::Array(elem) => Some(*elem),
+            found => {
+                self.error("E0202", array, format!("cannot index into {}", found));
+                None
+            }
+        }
+    }
+
+    /// Checks the arguments of a call, returning the type it returns (`None`
+    /// inside if it returns no value), or `None` if the call is invalid.
+    fn call(&mut self, expr: &'a Expr, name: &str, args: &'a [Expr]) -> Option<Option<Type>> {
+        if builtins::is_builtin(name) {
+            let mut types = Vec::new();
+            for arg in args {
+                types.push(self.expr(arg)?);
+            }
+            return match builtins::result_type(name, &types) {
+                Ok(ret) => Some(ret),
+                Err(usage) => {
+                    self.error("E0205", expr, usage);
+                    None
+                }
+            };
+        }
+        let function = *self.functions.get(name)?;
+        if args.len() != function.params.len() {
+            self.error(
+                "E0205",
+                expr,
+                format!(
+                    "function {} takes {} argument(s) but {} were given",
+                    name,
+                    function.params.len(),
+                    args.len()
+                ),
+            );
+        }
+        for (param, arg) in function.params.iter().zip(args) {
+            if let Some(found) = self.expr(arg) {
+                if found != param.typ {
+                    self.mismatch(arg, &param.typ, found, &format!("parameter {}", param.name));
+                }
+            }
+        }
+        Some(function.ret.clone())
+    }
+
+    fn mismatch(&mut self, expr: &Expr, expected: &Type, found: Type, target: &str) {
+        self.error(
+            "E0201",
+            expr,
+            format!("expected {} for {}, found {}", expected, target, found),
+        );
+    }
+    fn error(&mut self, code: &'static str, expr: &Expr, message: String) {
+        self.errors.push(Diagnostic::typ(code, expr.span, message));
+    }
+}
+
+/// Whether every path through `block` ends in a `return`.
+fn always_returns(block: &Block) -> bool {
+    block.iter().any(|stmt| match &stmt.kind {
+        StmtKind::Return(_) => true,
+        StmtKind::If(_, then_block, Some(else_block)) => {
+            always_returns(then_block) && always_returns(else_block)
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::Parser;
+
+    fn error_codes(source: &str) -> Vec<&'static str> {
+        let program = Parser::new(lex(source).unwrap()).parse().unwrap();
+        match Checker::new().check(&program) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.code).collect(),
+        }
+    }
+
+    #[test]
+    fn reports_redeclarations_in_the_same_scope() {
+        assert_eq!(error_codes("int x = 1;\nint x = 2;"), ["E0210"]);
+        assert_eq!(error_codes("fn f(int a, int a) {}"), ["E0210"]);
+        assert!(error_codes("int x = 1;\nif (true) { int x = 2; }").is_empty());
+        let source = "fn f() -> int { return 1; }\nfn f() -> int { return 2; }";
+        assert_eq!(error_codes(source), ["E0210"]);
+        let source =
+            "fn f(int a) -> int { return a; }\nprintln(f(1));\nfn f() -> int { return 2; }";
+        assert_eq!(error_codes(source), ["E0210"]);
+    }
+
+    #[test]
+    fn reports_calls_with_the_wrong_number_of_arguments() {
+        let source = "fn f(int a) -> int { return a; }\nint x = f(1, 2);";
+        assert_eq!(error_codes(source), ["E0205"]);
+        assert_eq!(error_codes("println(len());"), ["E0205"]);
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use crate::value::Value;
+
+/// The variables declared directly in one block.
+pub type Scope = HashMap<String, Value>;
+
+/// The chain of scopes visible to running code. Each function call gets its
+/// own stack of block scopes, which sees only itself and the globals.
+pub struct Environment {
+    frames: Vec<Vec<Scope>>,
+}
+impl Default for Environment {
+    fn default() -> Self {
+        Environment {
+            frames: vec![vec![Scope::new()]],
+        }
+    }
+}
+impl Environment {
+    pub fn globals(&self) -> &Scope {
+        &self.frames[0][0]
+    }
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn push_scope(&mut self) {
+        self.frames.last_mut().unwrap().push(Scope::new());
+    }
+    pub fn pop_scope(&mut self) {
+        self.frames.last_mut().unwrap().pop();
+    }
+    pub fn push_frame(&mut self) {
+        self.frames.push(vec![Scope::new()]);
+    }
+    pub fn pop_frame(&mut self) {
+        self.frames.pop();
+    }
+
+    /// Innermost to outermost: the current call's scopes, then the globals.
+    fn visible(&self) -> impl Iterator<Item = &Scope> {
+        let frame = self.frames.last().unwrap();
+        let globals = (self.frames.len() > 1).then(|| self.globals());
+        frame.iter().rev().chain(globals)
+    }
+    fn visible_mut(&mut self) -> impl Iterator<Item = &mut Scope> {
+        let (globals, calls) = self.frames.split_first_mut().unwrap();
+        match calls.last_mut() {
+            Some(frame) => frame.iter_mut().rev().chain(globals.first_mut()),
+            None => globals.iter_mut().rev().chain(None),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Value> {
+        self.visible().find_map(|scope| scope.get(id))
+    }
+    /// Declares `id` in the innermost scope, shadowing any outer variable.
+    pub fn declare(&mut self, id: &str, value: Value) {
+        let frame = self.frames.last_mut().unwrap();
+        frame.last_mut().unwrap().insert(id.to_string(), value);
+    }
+    /// Assigns to the innermost visible variable named `id`, returning false
+    /// if there is none.
+    pub fn assign(&mut self, id: &str, value: Value) -> bool {
+        match self.visible_mut().find_map(|scope| scope.get_mut(id)) {
+            Some(slot) => {
+                *slot = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::fmt;
+
+use crate::span::Span;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ErrorKind {
+    Lex,
+    Parse,
+    Type,
+    Runtime,
+}
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::Lex => write!(f, "lex error"),
+            ErrorKind::Parse => write!(f, "syntax error"),
+            ErrorKind::Type => write!(f, "type error"),
+            ErrorKind::Runtime => write!(f, "runtime error"),
+        }
+    }
+}
+
+/// An error in a `.oxi` program, located at `span`.
+///
+/// Codes are grouped by kind: `E00xx` lex, `E01xx` parse, `E02xx` type and
+/// `E03xx` runtime errors.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: ErrorKind,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+}
+impl Diagnostic {
+    pub fn new(
+        kind: ErrorKind,
+        code: &'static str,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Self {
+        Diagnostic {
+            kind,
+            code,
+            message: message.into(),
+            span,
+        }
+    }
+    pub fn lex(code: &'static str, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(ErrorKind::Lex, code, span, message)
+    }
+    pub fn parse(code: &'static str, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(ErrorKind::Parse, code, span, message)
+    }
+    pub fn typ(code: &'static str, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(ErrorKind::Type, code, span, message)
+    }
+    pub fn runtime(code: &'static str, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic::new(ErrorKind::Runtime, code, span, message)
+    }
+
+    /// Formats the diagnostic with its location and the offending source
+    /// line, with a caret under the span.
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let span = self.span;
+        let text = source
+            .lines()
+            .nth(span.line.saturating_sub(1))
+            .unwrap_or("");
+        let gutter = " ".repeat(span.line.to_string().len());
+        let width = source
+            .get(span.start..span.end)
+            .map_or(1, |s| s.lines().next().unwrap_or("").chars().count())
+            .max(1);
+        format!(
+            "{}[{}]: {}\n{gutter}--> {}:{}\n{gutter} |\n{} | {text}\n{gutter} | {}{}",
+            self.kind,
+            self.code,
+            self.message,
+            file,
+            span,
+            span.line,
+            " ".repeat(span.column.saturating_sub(1)),
+            "^".repeat(width)
+        )
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Diagnostic>;
@@ -0,0 +1,484 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::{BinaryOp, Expr, ExprKind, Function, Stmt, StmtKind};
+use crate::builtins;
+use crate::environment::{Environment, Scope};
+use crate::error::{Diagnostic, Result};
+use crate::random::Rng;
+use crate::span::Span;
+use crate::value::{Array, OpError, Value};
+
+/// How a statement finished: normally, by leaving the enclosing loop
+/// iteration, or by returning from the enclosing function.
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Option<Value>),
+}
+
+/// Calls nested deeper than this fail with a stack overflow error instead
+/// of overflowing the interpreter's own stack.
+const MAX_CALL_DEPTH: usize = 1000;
+
+/// Only programs that passed the checker are run, so values always have the
+/// types the checker worked out. Where that is relied on, a value of another
+/// type is a bug in the checker and panics rather than being reported.
+#[derive(Default)]
+pub struct Interpreter {
+    env: Environment,
+    functions: HashMap<String, Rc<Function>>,
+    rng: Rng,
+}
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::default()
+    }
+    /// An interpreter whose random built-ins give the same numbers on
+    /// every run with the same `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Interpreter {
+            rng: Rng::new(seed),
+            ..Interpreter::default()
+        }
+    }
+
+    pub fn globals(&self) -> &Scope {
+        self.env.globals()
+    }
+
+    pub fn execute(&mut self, program: &[Stmt]) -> Result<()> {
+        for stmt in program {
+            if let StmtKind::Function(function) = &stmt.kind {
+                self.functions
+                    .insert(function.name.clone(), function.clone());
+            }
+        }
+        self.statements(program)?;
+        Ok(())
+    }
+
+    /// Runs `stmts` in a new scope.
+    fn block(&mut self, stmts: &[Stmt]) -> Result<Flow> {
+        self.env.push_scope();
+        let flow = self.statements(stmts);
+        self.env.pop_scope();
+        flow
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) -> Result<Flow> {
+        for stmt in stmts {
+            match self.statement(stmt)? {
+                Flow::Next => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn statement(&mut self, stmt: &Stmt) -> Result<Flow> {
+        match &stmt.kind {
+            StmtKind::Declare {
+                name: id, value, ..
+            } => {
+                let value = self.eval(value)?;
+                self.env.declare(id, value);
+            }
+            StmtKind::Assign(id, value) => {
+                let value = self.eval(value)?;
+                if !self.env.assign(id, value) {
+                    return Err(undefined_variable(id, stmt.span));
+                }
+            }
+            StmtKind::SetIndex {
+                array,
+                index,
+                value,
+            } => {
+                let array = self.eval_array(array)?;
+                let i = self.eval_int(index)?;
+                let value = self.eval(value)?;
+                // Checked only now, since evaluating `value` may have
+                // changed the array's length.
+                let i = bounds(&array, i, index.span)?;
+                array.items.borrow_mut()[i] = value;
+            }
+            StmtKind::Print { args, newline } => {
+                let mut to_print = String::new();
+                for arg in args {
+                    to_print += &self.eval(arg)?.to_string();
+                }
+                if *newline {
+                    println!("{}", to_print);
+                } else {
+                    print!("{}", to_print);
+                }
+            }
+            StmtKind::If(condition, then_block, else_block) => {
+                if self.eval_bool(condition)? {
+                    return self.block(then_block);
+                } else if let Some(else_block) = else_block {
+                    return self.block(else_block);
+                }
+            }
+            StmtKind::While(condition, body) => {
+                while self.eval_bool(condition)? {
+                    if let Some(flow) = self.loop_body(body)? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            StmtKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.env.push_scope();
+                let flow = self.for_loop(init, condition, step, body);
+                self.env.pop_scope();
+                return flow;
+            }
+            StmtKind::ForRange {
+                var,
+                start,
+                end,
+                body,
+            } => {
+                let start = self.eval_int(start)?;
+                let end = self.eval_int(end)?;
+                for i in start..end {
+                    self.env.push_scope();
+                    self.env.declare(var, Value::Int(i));
+                    let flow = self.loop_body(body);
+                    self.env.pop_scope();
+                    if let Some(flow) = flow? {
+                        return Ok(flow);
+                    }
+                }
+            }
+            StmtKind::Break => return Ok(Flow::Break),
+            StmtKind::Continue => return Ok(Flow::Continue),
+            StmtKind::Function(_) => {}
+            StmtKind::Return(value) => {
+                let value = match value {
+                    Some(value) => Some(self.eval(value)?),
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StmtKind::Expr(expr) => match &expr.kind {
+                ExprKind::Call(name, args) => {
+                    self.call(name, args, expr.span)?;
+                }
+                _ => {
+                    self.eval(expr)?;
+                }
+            },
+        }
+        Ok(Flow::Next)
+    }
+
+    /// Runs one iteration of a loop body. Returns `None` to keep looping, or
+    /// the flow to leave the loop with: `Next` after a `break`, or a
+    /// `Return` to pass on.
+    fn loop_body(&mut self, body: &[Stmt]) -> Result<Option<Flow>> {
+        Ok(match self.block(body)? {
+            Flow::Next | Flow::Continue => None,
+            Flow::Break => Some(Flow::Next),
+            flow => Some(flow),
+        })
+    }
+
+    fn for_loop(
+        &mut self,
+        init: &Option<Box<Stmt>>,
+        condition: &Option<Expr>,
+        step: &Option<Box<Stmt>>,
+        body: &[Stmt],
+    ) -> Result<Flow> {
+        if let Some(init) = init {
+            self.statement(init)?;
+        }
+        loop {
+            if let Some(condition) = condition {
+                if !self.eval_bool(condition)? {
+                    break;
+                }
+            }
+            if let Some(flow) = self.loop_body(body)? {
+                return Ok(flow);
+            }
+            if let Some(step) = step {
+                self.statement(step)?;
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    fn get_var(&self, z: &str, span: Span) -> Result<Value> {
+        self.env
+            .get(z)
+            .cloned()
+            .ok_or_else(|| undefined_variable(z, span))
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], span: Span) -> Result<Option<Value>> {
+        if builtins::is_builtin(name) {
+            let mut values = Vec::new();
+            for arg in args {
+                values.push(self.eval(arg)?);
+            }
+            return builtins::call(name, values, &mut self.rng, span);
+        }
+        let Some(function) = self.functions.get(name).cloned() else {
+            return Err(Diagnostic::runtime(
+                "E0305",
+                span,
+                format!("undefined function {}", name),
+            ));
+        };
+        let mut values = Vec::new();
+        for arg in args {
+            values.push(self.eval(arg)?);
+        }
+        if self.env.depth() > MAX_CALL_DEPTH {
+            return Err(Diagnostic::runtime(
+                "E0306",
+                span,
+                format!("stack overflow calling {}", name),
+            ));
+        }
+
+        self.env.push_frame();
+        for (param, value) in function.params.iter().zip(values) {
+            self.env.declare(&param.name, value);
+        }
+        let flow = self.block(&function.body);
+        self.env.pop_frame();
+        let value = match flow? {
+            Flow::Return(value) => value,
+            _ => None,
+        };
+
+        match (&function.ret, &value) {
+            (Some(ret), None) => Err(Diagnostic::runtime(
+                "E0304",
+                function.span,
+                format!("{} ended without returning a value of type {}", name, ret),
+            )),
+            _ => Ok(value),
+        }
+    }
+
+    fn eval_bool(&mut self, expr: &Expr) -> Result<bool> {
+        match self.eval(expr)? {
+            Value::Bool(b) => Ok(b),
+            v => unreachable!("checked to be bool, found {:?}", v),
+        }
+    }
+
+    fn eval_int(&mut self, expr: &Expr) -> Result<i64> {
+        match self.eval(expr)? {
+            Value::Int(n) => Ok(n),
+            v => unreachable!("checked to be int, found {:?}", v),
+        }
+    }
+
+    fn eval_array(&mut self, expr: &Expr) -> Result<Rc<Array>> {
+        match self.eval(expr)? {
+            Value::Array(array) => Ok(array),
+            v => unreachable!("checked to be an array, found {:?}", v),
+        }
+    }
+
+    /// Evaluates `index` and checks it is in bounds for `array`.
+    fn index(&mut self, array: &Array, index: &Expr) -> Result<usize> {
+        let i = self.eval_int(index)?;
+        bounds(array, i, index.span)
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Result<Value> {
+        Ok(match &expr.kind {
+            ExprKind::Number(n) => Value::Int(*n),
+            ExprKind::Float(x) => Value::Float(*x),
+            ExprKind::BigInt(n) => Value::BigInt(n.clone()),
+            ExprKind::String(s) => Value::String(s.clone()),
+            ExprKind::Bool(b) => Value::Bool(*b),
+            ExprKind::Variable(id) => self.get_var(id, expr.span)?,
+            ExprKind::Unary(op, operand) => {
+                let v = self.eval(operand)?;
+                v.unary(*op).map_err(|e| match e {
+                    OpError::Overflow => Diagnostic::runtime(
+                        "E0309",
+                        expr.span,
+                        format!("integer overflow negating {}", v),
+                    ),
+                    _ => unreachable!("checked operand for {}: {:?}", op, v),
+                })?
+            }
+            ExprKind::Binary(lhs, op, rhs) => {
+                let a = self.eval(lhs)?;
+                // `&&` and `||` skip their right operand once the left one
+                // decides the result.
+                match (op, &a) {
+                    (BinaryOp::And, Value::Bool(false)) | (BinaryOp::Or, Value::Bool(true)) => {
+                        return Ok(a)
+                    }
+                    _ => {}
+                }
+                let b = self.eval(rhs)?;
+                a.binary(*op, &b)
+                    .map_err(|e| operator_error(e, *op, expr.span, &a, &b))?
+            }
+            ExprKind::Array(elem, items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(self.eval(item)?);
+                }
+                Value::array(elem.clone(), values)
+            }
+            ExprKind::Index(array, index) => {
+                let array = self.eval_array(array)?;
+                let i = self.index(&array, index)?;
+                let item = array.items.borrow()[i].clone();
+                item
+            }
+            ExprKind::Call(name, args) => self
+                .call(name, args, expr.span)?
+                .expect("checked to return a value"),
+            ExprKind::Cast(typ, operand) => {
+                let value = self.eval(operand)?;
+                value.cast(typ).ok_or_else(|| {
+                    Diagnostic::runtime(
+                        "E0309",
+                        expr.span,
+                        format!("{:?} does not fit in {}", value, typ),
+                    )
+                })?
+            }
+            ExprKind::Interpolated(parts) => {
+                let mut s = String::new();
+                for part in parts {
+                    s.push_str(&self.eval(part)?.to_string());
+                }
+                Value::String(s)
+            }
+        })
+    }
+}
+
+/// Checks that `i` is an index into `array`, reporting it at `span` if not.
+fn bounds(array: &Array, i: i64, span: Span) -> Result<usize> {
+    let len = array.items.borrow().len();
+    match usize::try_from(i) {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(Diagnostic::runtime(
+            "E0307",
+            span,
+            format!("index {} out of bounds for array of length {}", i, len),
+        )),
+    }
+}
+
+fn undefined_variable(id: &str, span: Span) -> Diagnostic {
+    Diagnostic::runtime("E0301", span, format!("undefined variable {}", id))
+}
+
+fn operator_error(e: OpError, op: BinaryOp, span: Span, a: &Value, b: &Value) -> Diagnostic {
+    match e {
+        OpError::Invalid => unreachable!("checked operands for {}: {:?} and {:?}", op, a, b),
+        OpError::Overflow => Diagnostic::runtime(
+            "E0309",
+            span,
+            format!("integer overflow in {} {} {}", a, op, b),
+        ),
+        OpError::DivisionByZero => {
+            let what = if op == BinaryOp::Rem {
+                "modulo"
+            } else {
+                "division"
+            };
+            Diagnostic::runtime("E0310", span, format!("{} by zero", what))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checker::Checker;
+    use crate::lexer::lex;
+    use crate::parser::Parser;
+
+    fn run(source: &str) -> std::result::Result<Interpreter, Vec<Diagnostic>> {
+        let program = Parser::new(lex(source).map_err(|e| vec![e])?).parse()?;
+        Checker::new().check(&program)?;
+        let mut interpreter = Interpreter::with_seed(0);
+        interpreter.execute(&program).map_err(|e| vec![e])?;
+        Ok(interpreter)
+    }
+
+    fn error(source: &str) -> Diagnostic {
+        match run(source) {
+            Ok(_) => panic!("expected an error from {:?}", source),
+            Err(mut errors) => errors.remove(0),
+        }
+    }
+
+    fn error_code(source: &str) -> &'static str {
+        error(source).code
+    }
+
+    fn global(interpreter: &Interpreter, name: &str) -> String {
+        format!("{:?}", interpreter.globals()[name])
+    }
+
+    #[test]
+    fn set_index_checks_bounds_after_the_value() {
+        assert_eq!(
+            error_code("int[] xs = [1, 2, 3]; xs[2] = pop(xs);"),
+            "E0307"
+        );
+        let interpreter = run("int[] xs = [1, 2, 3]; xs[1] = pop(xs);").unwrap();
+        assert_eq!(global(&interpreter, "xs"), "[1, 3]");
+    }
+
+    #[test]
+    fn indexed_array_literals_ignore_the_expected_type() {
+        let source = "fn f(int[] a) -> int { return len(a); }\n\
+                      int[] y = [[1, 2]][0];\n\
+                      int n = f([[1]][0]);\n\
+                      int z = [[1, 2], [3]][1][0];";
+        let interpreter = run(source).unwrap();
+        assert_eq!(global(&interpreter, "y"), "[1, 2]");
+        assert_eq!(global(&interpreter, "n"), "1");
+        assert_eq!(global(&interpreter, "z"), "3");
+    }
+
+    #[test]
+    fn short_circuits_logical_operators() {
+        let source = "int[] xs = [];\n\
+                      bool a = false && pop(xs) == 1;\n\
+                      bool b = true || pop(xs) == 1;\n\
+                      bool c = true && len(xs) == 0;";
+        let interpreter = run(source).unwrap();
+        assert_eq!(global(&interpreter, "a"), "false");
+        assert_eq!(global(&interpreter, "b"), "true");
+        assert_eq!(global(&interpreter, "c"), "true");
+        assert_eq!(
+            error_code("int[] xs = []; bool a = true && pop(xs) == 1;"),
+            "E0308"
+        );
+    }
+
+    #[test]
+    fn reports_division_by_zero_at_the_operator() {
+        for source in ["int x = 7 / (1 - 1);", "int x = 7 % (1 - 1);"] {
+            let e = error(source);
+            assert_eq!((e.code, e.span.start, e.span.end), ("E0310", 10, 11));
+        }
+        assert_eq!(error_code("bigint x = 7n / 0n;"), "E0310");
+    }
+}
@@ -0,0 +1,785 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::bigint::BigInt;
+use crate::error::{Diagnostic, Result};
+use crate::span::Span;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TokenKind {
+    Identifier(String),
+    Keyword(String),
+    String(String),
+    Number(i64),
+    Float(f64),
+    BigInt(BigInt),
+    True,
+    False,
+    Or,
+    And,
+    Plus,
+    Minus,
+    Asterisk,
+    Slash,
+    Percent,
+    Assign,
+    Semicolon,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    LessThan,
+    LessThanEqual,
+    GreaterThan,
+    GreaterThanEqual,
+    EqualTo,
+    NotEqual,
+    Bang,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Arrow,
+    DotDot,
+    /// A string literal containing `{expr}` interpolations.
+    Interpolated(Vec<StringPart>),
+    /// The text of a `///` comment line, without the slashes.
+    DocComment(String),
+}
+
+impl fmt::Display for TokenKind {
+    /// Writes the token as it appears in the source, in backticks.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            TokenKind::Identifier(name) | TokenKind::Keyword(name) => {
+                return write!(f, "`{}`", name)
+            }
+            TokenKind::String(s) => return write!(f, "`{:?}`", s),
+            TokenKind::Number(n) => return write!(f, "`{}`", n),
+            TokenKind::Float(x) => return write!(f, "`{:?}`", x),
+            TokenKind::BigInt(n) => return write!(f, "`{}n`", n),
+            TokenKind::Interpolated(_) => return write!(f, "string literal"),
+            TokenKind::DocComment(_) => return write!(f, "doc comment"),
+            TokenKind::True => "true",
+            TokenKind::False => "false",
+            TokenKind::Or => "||",
+            TokenKind::And => "&&",
+            TokenKind::Plus => "+",
+            TokenKind::Minus => "-",
+            TokenKind::Asterisk => "*",
+            TokenKind::Slash => "/",
+            TokenKind::Percent => "%",
+            TokenKind::Assign => "=",
+            TokenKind::Semicolon => ";",
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBrace => "{",
+            TokenKind::CloseBrace => "}",
+            TokenKind::LessThan => "<",
+            TokenKind::LessThanEqual => "<=",
+            TokenKind::GreaterThan => ">",
+            TokenKind::GreaterThanEqual => ">=",
+            TokenKind::EqualTo => "==",
+            TokenKind::NotEqual => "!=",
+            TokenKind::Bang => "!",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Arrow => "->",
+            TokenKind::DotDot => "..",
+        };
+        write!(f, "`{}`", symbol)
+    }
+}
+
+/// A piece of an interpolated string literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart {
+    Text(String),
+    /// The tokens of an interpolated expression followed by its closing
+    /// `}`.
+    Expr(Vec<Token>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+pub trait CheckChar {
+    fn is_letter(&self) -> bool;
+    fn is_number(&self) -> bool;
+}
+
+impl CheckChar for char {
+    fn is_letter(&self) -> bool {
+        self.is_alphabetic() || *self == '_'
+    }
+
+    fn is_number(&self) -> bool {
+        self.is_ascii_digit()
+    }
+}
+pub trait CheckStr {
+    fn is_keyword(&self) -> bool;
+}
+impl CheckStr for str {
+    fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            "if" | "else"
+                | "while"
+                | "print"
+                | "println"
+                | "int"
+                | "float"
+                | "bigint"
+                | "string"
+                | "bool"
+                | "fn"
+                | "return"
+                | "for"
+                | "in"
+                | "break"
+                | "continue"
+        )
+    }
+}
+
+/// Character iterator that keeps track of the byte offset, line and column
+/// of the next character.
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+impl<'a> Cursor<'a> {
+    fn new(input: &'a str) -> Self {
+        Cursor {
+            chars: input.chars().peekable(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+    fn next_if(&mut self, f: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.peek() {
+            Some(c) if f(c) => self.next(),
+            _ => None,
+        }
+    }
+    /// The character `n` places after the next one.
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.clone().nth(n)
+    }
+    /// Consumes a run of digits onto the end of `text`.
+    fn digits(&mut self, text: &mut String) {
+        while let Some(&c) = self.peek() {
+            if !c.is_number() {
+                break;
+            }
+            text.push(c);
+            self.next();
+        }
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            end: self.pos,
+            ..start
+        }
+    }
+    fn here(&self) -> Span {
+        Span {
+            start: self.pos,
+            end: self.pos,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+pub fn lex(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = Cursor::new(input);
+    while let Some(token) = token(&mut chars)? {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Lexes the next token, skipping whitespace and comments, or returns
+/// `None` at the end of the input.
+fn token(chars: &mut Cursor) -> Result<Option<Token>> {
+    while let Some(&c) = chars.peek() {
+        let start = chars.here();
+        chars.next();
+        let kind = match c {
+            _ if c.is_whitespace() => continue,
+            '+' => TokenKind::Plus,
+            '-' => {
+                if chars.peek() == Some(&'>') {
+                    chars.next();
+                    TokenKind::Arrow
+                } else {
+                    TokenKind::Minus
+                }
+            }
+            '*' => TokenKind::Asterisk,
+            '/' if chars.peek() == Some(&'/') => {
+                chars.next();
+                // `///` starts a doc comment, but `////...` is a plain one.
+                let doc = chars.peek() == Some(&'/') && chars.peek_nth(1) != Some('/');
+                let mut text = String::new();
+                while let Some(c) = chars.next_if(|&c| c != '\n') {
+                    text.push(c);
+                }
+                if !doc {
+                    continue;
+                }
+                let text = text[1..].strip_prefix(' ').unwrap_or(&text[1..]);
+                TokenKind::DocComment(text.trim_end().to_string())
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                block_comment(chars, start)?;
+                continue;
+            }
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '=' => {
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    TokenKind::EqualTo
+                } else {
+                    TokenKind::Assign
+                }
+            }
+            '!' => {
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    TokenKind::NotEqual
+                } else {
+                    TokenKind::Bang
+                }
+            }
+            ';' => TokenKind::Semicolon,
+            '(' => TokenKind::OpenParen,
+            ')' => TokenKind::CloseParen,
+            '{' => TokenKind::OpenBrace,
+            '}' => TokenKind::CloseBrace,
+            '[' => TokenKind::OpenBracket,
+            ']' => TokenKind::CloseBracket,
+            ',' => TokenKind::Comma,
+            '.' if chars.peek() == Some(&'.') => {
+                chars.next();
+                TokenKind::DotDot
+            }
+            '<' => {
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    TokenKind::LessThanEqual
+                } else {
+                    TokenKind::LessThan
+                }
+            }
+            '>' => {
+                if chars.peek() == Some(&'=') {
+                    chars.next();
+                    TokenKind::GreaterThanEqual
+                } else {
+                    TokenKind::GreaterThan
+                }
+            }
+            '"' => string(chars, start)?,
+            'r' if raw_hashes(chars).is_some() => {
+                let hashes = raw_hashes(chars).unwrap();
+                for _ in 0..=hashes {
+                    chars.next();
+                }
+                TokenKind::String(raw_string(chars, start, hashes)?)
+            }
+            _ if c.is_letter() => {
+                let mut identifier = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_letter() || c.is_number() {
+                        identifier.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if identifier == "true" {
+                    TokenKind::True
+                } else if identifier == "false" {
+                    TokenKind::False
+                } else if identifier.is_keyword() {
+                    TokenKind::Keyword(identifier)
+                } else {
+                    TokenKind::Identifier(identifier)
+                }
+            }
+            _ if c.is_number() => {
+                let mut text = c.to_string();
+                chars.digits(&mut text);
+                let mut float = false;
+                // A `.` only starts a fraction if a digit follows, so that
+                // ranges like `0..10` still lex as two numbers.
+                if chars.peek() == Some(&'.') && chars.peek_nth(1).is_some_and(|c| c.is_number()) {
+                    float = true;
+                    text.push('.');
+                    chars.next();
+                    chars.digits(&mut text);
+                }
+                let sign = matches!(chars.peek_nth(1), Some('+' | '-')) as usize;
+                if matches!(chars.peek(), Some('e' | 'E'))
+                    && chars.peek_nth(1 + sign).is_some_and(|c| c.is_number())
+                {
+                    float = true;
+                    for _ in 0..=sign {
+                        text.extend(chars.next());
+                    }
+                    chars.digits(&mut text);
+                }
+                let suffix = chars.peek() == Some(&'n')
+                    && !chars
+                        .peek_nth(1)
+                        .is_some_and(|c| c.is_letter() || c.is_number());
+                if float {
+                    let x: f64 = text.parse().unwrap();
+                    if x.is_infinite() {
+                        return Err(Diagnostic::lex(
+                            "E0003",
+                            chars.span_from(start),
+                            format!("float literal {} is too large for float", text),
+                        ));
+                    }
+                    TokenKind::Float(x)
+                } else if suffix {
+                    chars.next();
+                    TokenKind::BigInt(BigInt::from_digits(&text))
+                } else {
+                    match text.parse() {
+                        Ok(n) => TokenKind::Number(n),
+                        Err(_) => {
+                            return Err(Diagnostic::lex(
+                                "E0003",
+                                chars.span_from(start),
+                                format!(
+                                "integer literal {} is too large for int; write {}n for a bigint",
+                                text, text
+                            ),
+                            ))
+                        }
+                    }
+                }
+            }
+            '&' if chars.peek() == Some(&'&') => {
+                chars.next();
+                TokenKind::And
+            }
+            '|' if chars.peek() == Some(&'|') => {
+                chars.next();
+                TokenKind::Or
+            }
+            '&' | '|' => {
+                return Err(Diagnostic::lex(
+                    "E0002",
+                    chars.span_from(start),
+                    format!("expected `{c}{c}`"),
+                ))
+            }
+            _ => {
+                return Err(Diagnostic::lex(
+                    "E0001",
+                    chars.span_from(start),
+                    format!("unexpected character `{}`", c),
+                ))
+            }
+        };
+        return Ok(Some(Token {
+            kind,
+            span: chars.span_from(start),
+        }));
+    }
+    Ok(None)
+}
+
+/// Lexes the rest of a string literal after its opening `"`, processing
+/// escapes. Strings may span lines; a `\` at the end of a line removes the
+/// line break and the next line's leading whitespace.
+///
+/// Each `{expr}` in the string is lexed as tokens to be parsed as an
+/// expression; `\{` and `\}` are literal braces.
+fn string(chars: &mut Cursor, start: Span) -> Result<TokenKind> {
+    let mut parts = Vec::new();
+    let mut s = String::new();
+    loop {
+        let escape = chars.here();
+        match chars.next() {
+            None => return Err(unterminated(start)),
+            Some('"') if parts.is_empty() => return Ok(TokenKind::String(s)),
+            Some('"') => {
+                if !s.is_empty() {
+                    parts.push(StringPart::Text(s));
+                }
+                return Ok(TokenKind::Interpolated(parts));
+            }
+            Some('{') => {
+                let tokens = interpolation(chars, escape)?;
+                if tokens.len() == 1 {
+                    return Err(Diagnostic::lex(
+                        "E0008",
+                        chars.span_from(escape),
+                        "expected an expression between the braces; write \\{ for a literal brace",
+                    ));
+                }
+                if !s.is_empty() {
+                    parts.push(StringPart::Text(std::mem::take(&mut s)));
+                }
+                parts.push(StringPart::Expr(tokens));
+            }
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('0') => s.push('\0'),
+                Some('\\') => s.push('\\'),
+                Some('"') => s.push('"'),
+                Some(c @ ('{' | '}')) => s.push(c),
+                Some('\n' | '\r') => {
+                    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                        chars.next();
+                    }
+                }
+                Some('u') => s.push(unicode_escape(chars, escape)?),
+                Some(c) => {
+                    return Err(Diagnostic::lex(
+                        "E0004",
+                        chars.span_from(escape),
+                        format!("unknown escape sequence \\{}", c.escape_default()),
+                    ))
+                }
+                None => return Err(unterminated(start)),
+            },
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+/// Lexes the tokens of an interpolated expression after the `{` at `open`,
+/// up to and including the matching `}`. A `{` that is never closed takes
+/// the rest of the input with it, usually ending inside another string, so
+/// that is reported at the brace instead. Other errors are reported there
+/// too, as the brace may have been meant literally, unless they come from
+/// an interpolation nested inside this one.
+fn interpolation(chars: &mut Cursor, open: Span) -> Result<Vec<Token>> {
+    let brace = Span {
+        end: open.start + 1,
+        ..open
+    };
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    loop {
+        let token = match token(chars) {
+            Ok(Some(token)) => token,
+            Err(e) if matches!(e.code, "E0008" | "E0009" | "E0010") => return Err(e),
+            Err(e) if e.code != "E0006" => {
+                return Err(Diagnostic::lex(
+                    "E0010",
+                    brace,
+                    format!(
+                        "{} in the expression after this {{; write \\{{ for a literal brace",
+                        e.message
+                    ),
+                ))
+            }
+            _ => {
+                return Err(Diagnostic::lex(
+                    "E0009",
+                    brace,
+                    "unclosed { in string literal; write \\{ for a literal brace",
+                ))
+            }
+        };
+        match token.kind {
+            TokenKind::OpenBrace => depth += 1,
+            TokenKind::CloseBrace if depth == 0 => {
+                tokens.push(token);
+                return Ok(tokens);
+            }
+            TokenKind::CloseBrace => depth -= 1,
+            _ => {}
+        }
+        tokens.push(token);
+    }
+}
+
+/// Lexes the `{...}` of a `\u{...}` escape starting at `escape`.
+fn unicode_escape(chars: &mut Cursor, escape: Span) -> Result<char> {
+    let mut hex = String::new();
+    let braced = chars.peek() == Some(&'{');
+    if braced {
+        chars.next();
+        while let Some(&c) = chars.peek() {
+            if !c.is_ascii_hexdigit() || hex.len() == 6 {
+                break;
+            }
+            hex.push(c);
+            chars.next();
+        }
+    }
+    if !braced || hex.is_empty() || chars.next() != Some('}') {
+        return Err(Diagnostic::lex(
+            "E0005",
+            chars.span_from(escape),
+            "invalid unicode escape, expected \\u{...} with 1 to 6 hex digits",
+        ));
+    }
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| {
+            Diagnostic::lex(
+                "E0005",
+                chars.span_from(escape),
+                format!("\\u{{{}}} is not a unicode character", hex),
+            )
+        })
+}
+
+/// If the `r` just consumed starts a raw string (`r"..."`, `r#"..."#`, ...),
+/// the number of `#`s before its opening quote.
+fn raw_hashes(chars: &Cursor) -> Option<usize> {
+    let mut hashes = 0;
+    loop {
+        match chars.peek_nth(hashes)? {
+            '#' => hashes += 1,
+            '"' => return Some(hashes),
+            _ => return None,
+        }
+    }
+}
+
+/// Lexes the rest of a raw string after its opening quote. Nothing is
+/// escaped; the string ends at a `"` followed by `hashes` `#`s.
+fn raw_string(chars: &mut Cursor, start: Span, hashes: usize) -> Result<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            None => return Err(unterminated(start)),
+            Some('"') if (0..hashes).all(|i| chars.peek_nth(i) == Some('#')) => {
+                for _ in 0..hashes {
+                    chars.next();
+                }
+                return Ok(s);
+            }
+            Some(c) => s.push(c),
+        }
+    }
+}
+
+/// Reported at the opening quote or `r` of the literal.
+fn unterminated(start: Span) -> Diagnostic {
+    Diagnostic::lex(
+        "E0006",
+        Span {
+            end: start.start + 1,
+            ..start
+        },
+        "unterminated string literal",
+    )
+}
+
+/// Skips the rest of a `/* */` comment after its opening `/*`. Block
+/// comments nest, so each `/*` inside needs its own `*/`.
+fn block_comment(chars: &mut Cursor, start: Span) -> Result<()> {
+    let mut depth = 1;
+    while depth > 0 {
+        match chars.next() {
+            Some('/') if chars.peek() == Some(&'*') => {
+                chars.next();
+                depth += 1;
+            }
+            Some('*') if chars.peek() == Some(&'/') => {
+                chars.next();
+                depth -= 1;
+            }
+            Some(_) => {}
+            None => {
+                return Err(Diagnostic::lex(
+                    "E0007",
+                    Span {
+                        end: start.start + 2,
+                        ..start
+                    },
+                    "unterminated block comment",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        lex(source).unwrap().into_iter().map(|t| t.kind).collect()
+    }
+
+    fn string(source: &str) -> String {
+        match kinds(source).as_slice() {
+            [TokenKind::String(s)] => s.clone(),
+            kinds => panic!("expected a string literal, found {:?}", kinds),
+        }
+    }
+
+    fn parts(source: &str) -> Vec<StringPart> {
+        match kinds(source).as_slice() {
+            [TokenKind::Interpolated(parts)] => parts.clone(),
+            kinds => panic!("expected an interpolated string, found {:?}", kinds),
+        }
+    }
+
+    /// The code of the error lexing `source` and the byte range it covers.
+    fn error(source: &str) -> (&'static str, usize, usize) {
+        match lex(source) {
+            Ok(tokens) => panic!("expected an error, found {:?}", tokens),
+            Err(e) => (e.code, e.span.start, e.span.end),
+        }
+    }
+
+    #[test]
+    fn processes_escapes() {
+        assert_eq!(string(r#""a\n\t\r\0\\\"\{\}""#), "a\n\t\r\0\\\"{}");
+        assert_eq!(string(r#""\u{41}\u{e9}\u{10FFFF}""#), "A\u{e9}\u{10FFFF}");
+        assert_eq!(error(r#""ab\q""#), ("E0004", 3, 5));
+        assert_eq!(error(r#""\u{110000}""#).0, "E0005");
+        assert_eq!(error(r#""\u{D800}""#).0, "E0005");
+        assert_eq!(error(r#""\u{}""#).0, "E0005");
+        assert_eq!(error(r#""\u{1234567}""#).0, "E0005");
+        assert_eq!(error(r#""\u41""#).0, "E0005");
+    }
+
+    #[test]
+    fn joins_lines_after_a_continuation() {
+        assert_eq!(string("\"one \\\n      two\""), "one two");
+        assert_eq!(string("\"one \\\r\n\ttwo\""), "one two");
+        assert_eq!(string("\"one\ntwo\""), "one\ntwo");
+    }
+
+    #[test]
+    fn matches_the_hashes_of_raw_strings() {
+        assert_eq!(string(r#"r"a\n{b}""#), r"a\n{b}");
+        assert_eq!(string(r###"r#"say "hi""#"###), r#"say "hi""#);
+        assert_eq!(string(r###"r##"a"#b"##"###), r##"a"#b"##);
+        assert_eq!(error(r###"x = r#"abc";"###), ("E0006", 4, 5));
+        assert_eq!(kinds("r"), [TokenKind::Identifier(String::from("r"))]);
+    }
+
+    #[test]
+    fn reports_unterminated_literals_at_their_start() {
+        assert_eq!(error("int x = \"abc;\n"), ("E0006", 8, 9));
+        assert_eq!(error("\"abc\\"), ("E0006", 0, 1));
+        assert_eq!(error("1 /* a /* b */"), ("E0007", 2, 4));
+    }
+
+    #[test]
+    fn nests_block_comments() {
+        let one_two = [TokenKind::Number(1), TokenKind::Number(2)];
+        assert_eq!(kinds("1 /* a /* b */ c */ 2"), one_two);
+        assert_eq!(kinds("1 /**/ 2 /* /**/ */"), one_two);
+        assert_eq!(
+            kinds("1 /* */ */"),
+            [TokenKind::Number(1), TokenKind::Asterisk, TokenKind::Slash]
+        );
+    }
+
+    #[test]
+    fn takes_only_three_slashes_as_a_doc_comment() {
+        let source = "/// The answer.  \n//// Not a doc comment.\n// Nor this.\n///\nint";
+        assert_eq!(
+            kinds(source),
+            [
+                TokenKind::DocComment(String::from("The answer.")),
+                TokenKind::DocComment(String::new()),
+                TokenKind::Keyword(String::from("int")),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_interpolated_expressions() {
+        let found = parts(r#""a{x}b{len("}")}""#);
+        let expr_kinds = |part: &StringPart| match part {
+            StringPart::Expr(tokens) => tokens.iter().map(|t| t.kind.clone()).collect(),
+            StringPart::Text(_) => Vec::new(),
+        };
+        assert_eq!(found.len(), 4);
+        assert_eq!(found[0], StringPart::Text(String::from("a")));
+        assert_eq!(
+            expr_kinds(&found[1]),
+            [
+                TokenKind::Identifier(String::from("x")),
+                TokenKind::CloseBrace
+            ]
+        );
+        assert_eq!(found[2], StringPart::Text(String::from("b")));
+        assert_eq!(
+            expr_kinds(&found[3]),
+            [
+                TokenKind::Identifier(String::from("len")),
+                TokenKind::OpenParen,
+                TokenKind::String(String::from("}")),
+                TokenKind::CloseParen,
+                TokenKind::CloseBrace,
+            ]
+        );
+        let nested = parts(r#""{ {x} }""#);
+        assert_eq!(expr_kinds(&nested[0]).len(), 4);
+    }
+
+    #[test]
+    fn reports_empty_and_unclosed_interpolations() {
+        assert_eq!(error(r#"s = "a{}b";"#), ("E0008", 6, 8));
+        assert_eq!(error(r#"s = "a{x";"#), ("E0009", 6, 7));
+        assert_eq!(error(r#"s = "a{x" + "b";"#), ("E0009", 6, 7));
+        assert_eq!(error(r#"s = "{\"a\": 1}";"#), ("E0010", 5, 6));
+        assert_eq!(error(r#"s = "a{x + 1e400}";"#), ("E0010", 6, 7));
+        assert_eq!(error(r#"s = "{f("{\q}")}";"#), ("E0010", 9, 10));
+    }
+
+    #[test]
+    fn rejects_number_literals_out_of_range() {
+        assert_eq!(kinds("9223372036854775807"), [TokenKind::Number(i64::MAX)]);
+        assert_eq!(error("9223372036854775808"), ("E0003", 0, 19));
+        assert_eq!(
+            kinds("9223372036854775808n"),
+            [TokenKind::BigInt(
+                BigInt::parse("9223372036854775808").unwrap()
+            )]
+        );
+        assert_eq!(
+            kinds("1.5e308 1e-400"),
+            [TokenKind::Float(1.5e308), TokenKind::Float(0.0)]
+        );
+        assert_eq!(error("x = 1e400;"), ("E0003", 4, 9));
+        assert_eq!(error("x = 2.0e308;"), ("E0003", 4, 11));
+    }
+}
@@ -0,0 +1,1092 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ast::{BinaryOp, Block, Expr, ExprKind, Function, Param, Stmt, StmtKind, Type, UnaryOp};
+use crate::builtins;
+use crate::error::{Diagnostic, Result};
+use crate::lexer::{StringPart, Token, TokenKind};
+use crate::span::Span;
+
+#[derive(Clone)]
+struct Signature {
+    params: Vec<Type>,
+    ret: Option<Type>,
+}
+
+/// A function's name, parameters and return type.
+type Header = (String, Vec<Param>, Option<Type>);
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Span of the most recently consumed token.
+    prev: Span,
+    /// Declared variable types, one map per enclosing block. Used to reject
+    /// undeclared names and to work out type hints.
+    scopes: Vec<HashMap<String, Type>>,
+    functions: HashMap<String, Signature>,
+    /// Functions whose header has a syntax error. Calls to them are parsed
+    /// without a signature rather than reported as undeclared.
+    broken_functions: HashSet<String>,
+    /// Return type of the function whose body is being parsed, `None` at
+    /// the top level.
+    returns: Option<Option<Type>>,
+    /// Type expected by the context of the expression being parsed.
+    hint: Option<Type>,
+    depth: usize,
+    /// Number of loops enclosing the statement being parsed.
+    loops: usize,
+    /// Syntax errors reported so far; parsing carries on after each one.
+    errors: Vec<Diagnostic>,
+}
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            prev: Span::default(),
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            broken_functions: HashSet::new(),
+            returns: None,
+            hint: None,
+            depth: 0,
+            loops: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+    /// Span of the next token, or an empty span just past the last one.
+    fn peek_span(&self) -> Span {
+        match self.tokens.get(self.pos) {
+            Some(t) => t.span,
+            None => Span {
+                start: self.prev.end,
+                column: self.prev.column + (self.prev.end - self.prev.start),
+                ..self.prev
+            },
+        }
+    }
+    fn next(&mut self) -> Option<TokenKind> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        self.prev = token.span;
+        Some(token.kind.clone())
+    }
+    /// Consumes the next token, failing with `what` was expected if the file
+    /// ends here.
+    fn advance(&mut self, what: &str) -> Result<TokenKind> {
+        let span = self.peek_span();
+        self.next().ok_or_else(|| {
+            Diagnostic::parse(
+                "E0102",
+                span,
+                format!("expected {}, found end of file", what),
+            )
+        })
+    }
+    /// Reports the token just consumed as unexpected. The token is put back
+    /// so that `synchronize` can treat it as a possible recovery point.
+    fn unexpected<T>(&mut self, what: &str, found: TokenKind) -> Result<T> {
+        self.pos -= 1;
+        Err(Diagnostic::parse(
+            "E0101",
+            self.prev,
+            format!("expected {}, found {}", what, found),
+        ))
+    }
+
+    fn expect(&mut self, expected: TokenKind) -> Result<()> {
+        let what = expected.to_string();
+        match self.advance(&what)? {
+            found if found == expected => Ok(()),
+            found => self.unexpected(&what, found),
+        }
+    }
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(TokenKind::Keyword(k)) if k == keyword)
+    }
+    /// Whether the next token starts a variable or function declaration.
+    fn at_declaration(&self) -> bool {
+        matches!(self.peek(), Some(TokenKind::Keyword(k)) if Type::from_keyword(k).is_some() || k == "fn")
+    }
+
+    fn lookup(&self, name: &str) -> Option<Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+    }
+    /// Declares `name` in the innermost scope. Redeclarations are left to
+    /// the checker.
+    fn declare(&mut self, name: &str, typ: Type) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), typ);
+    }
+    /// A reference to the variable `name`, which must be in scope.
+    fn variable(&self, name: String) -> Result<ExprKind> {
+        if self.lookup(&name).is_none() {
+            return Err(Diagnostic::parse(
+                "E0109",
+                self.prev,
+                format!("use of undeclared variable {}", name),
+            ));
+        }
+        Ok(ExprKind::Variable(name))
+    }
+
+    fn type_name(&mut self) -> Result<Type> {
+        match self.advance("type")? {
+            TokenKind::Keyword(k) if Type::from_keyword(&k).is_some() => {
+                self.array_suffix(Type::from_keyword(&k).unwrap())
+            }
+            t => self.unexpected("type", t),
+        }
+    }
+    /// Wraps `typ` in an array type for each `[]` following it.
+    fn array_suffix(&mut self, mut typ: Type) -> Result<Type> {
+        while self.peek() == Some(&TokenKind::OpenBracket) {
+            self.next();
+            self.expect(TokenKind::CloseBracket)?;
+            typ = Type::Array(Box::new(typ));
+        }
+        Ok(typ)
+    }
+    fn identifier(&mut self) -> Result<String> {
+        match self.advance("identifier")? {
+            TokenKind::Identifier(id) => Ok(id),
+            t => self.unexpected("identifier", t),
+        }
+    }
+
+    /// Parses the whole program, returning every syntax error found rather
+    /// than stopping at the first.
+    pub fn parse(&mut self) -> std::result::Result<Block, Vec<Diagnostic>> {
+        self.collect_signatures();
+        let mut program = Vec::new();
+        while self.peek().is_some() {
+            if let Some(stmt) = self.recovering_statement() {
+                program.push(stmt);
+            }
+        }
+        if self.errors.is_empty() {
+            Ok(program)
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+
+    /// Records the signature of every `fn` in the file up front so calls can
+    /// be parsed before the function they refer to.
+    fn collect_signatures(&mut self) {
+        for i in 0..self.tokens.len() {
+            if self.tokens[i].kind != TokenKind::Keyword(String::from("fn")) {
+                continue;
+            }
+            self.pos = i + 1;
+            match self.function_header() {
+                Ok((name, params, ret)) => {
+                    let params = params.into_iter().map(|p| p.typ).collect();
+                    // A redefinition is reported by the checker, which keeps
+                    // the first definition; calls are hinted against it too.
+                    self.functions
+                        .entry(name)
+                        .or_insert(Signature { params, ret });
+                }
+                Err(_) => {
+                    if let Some(TokenKind::Identifier(name)) =
+                        self.tokens.get(i + 1).map(|t| &t.kind)
+                    {
+                        self.broken_functions.insert(name.clone());
+                    }
+                }
+            }
+        }
+        self.pos = 0;
+        self.prev = Span::default();
+    }
+
+    /// Parses a statement; on a syntax error the error is recorded and the
+    /// parser skips ahead to the next statement boundary.
+    fn recovering_statement(&mut self) -> Option<Stmt> {
+        let start = self.pos;
+        match self.statement() {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                self.errors.push(e);
+                if self.pos == start {
+                    self.next();
+                }
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    /// Skips tokens until just after a `;`, or until a `}` closing the
+    /// enclosing block or a keyword that starts a new statement. A `{` on
+    /// the way is parsed as a block so errors inside it are still reported.
+    ///
+    /// A type keyword only counts as the start of a statement if the error
+    /// was reported at it, as after a missing `;`. Further on it is more
+    /// likely the type of a parameter or loop variable.
+    fn synchronize(&mut self) {
+        let first = self.pos;
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::Semicolon => {
+                    self.next();
+                    return;
+                }
+                TokenKind::OpenBrace => {
+                    if let Err(e) = self.block() {
+                        self.errors.push(e);
+                    }
+                    return;
+                }
+                TokenKind::CloseBrace => return,
+                TokenKind::Keyword(_) if self.pos == first && self.at_declaration() => return,
+                TokenKind::Keyword(k) if starts_statement(k) => return,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+
+    /// Records an error in a loop header and recovers from it with the
+    /// loop's context still in place, so that a body reached while
+    /// synchronizing may use the loop variable and `break`.
+    fn skip_loop(&mut self, e: Diagnostic) {
+        self.errors.push(e);
+        self.loops += 1;
+        self.synchronize();
+        self.loops -= 1;
+    }
+
+    /// Records an error in an `if` statement and recovers from it, also
+    /// skipping the rest of its `else` chain so that an `else` is not taken
+    /// for one without an `if`. Errors in the blocks skipped are still
+    /// reported.
+    fn skip_if(&mut self, e: Diagnostic) {
+        self.errors.push(e);
+        self.synchronize();
+        while self.at_keyword("else") {
+            self.next();
+            if !self.at_keyword("if") {
+                if let Err(e) = self.block() {
+                    self.errors.push(e);
+                }
+                return;
+            }
+            self.next();
+            match self.if_statement() {
+                Ok(_) => return,
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                }
+            }
+        }
+    }
+
+    /// Records an error in a function header and skips the rest of the
+    /// function. Without its parameters the body can't be checked, so
+    /// nothing in it is reported.
+    fn skip_function(&mut self, e: Diagnostic) {
+        self.errors.push(e);
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::Semicolon => {
+                    self.next();
+                    return;
+                }
+                TokenKind::OpenBrace => {
+                    let mut depth = 0;
+                    while let Some(kind) = self.next() {
+                        match kind {
+                            TokenKind::OpenBrace => depth += 1,
+                            TokenKind::CloseBrace if depth == 1 => return,
+                            TokenKind::CloseBrace => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                    return;
+                }
+                TokenKind::CloseBrace => return,
+                _ => {}
+            }
+            self.next();
+        }
+    }
+
+    fn block(&mut self) -> Result<Block> {
+        self.expect(TokenKind::OpenBrace)?;
+        let open = self.prev;
+        let mut block = Vec::new();
+        self.depth += 1;
+        self.scopes.push(HashMap::new());
+        let result = loop {
+            match self.peek() {
+                Some(TokenKind::CloseBrace) => {
+                    self.next();
+                    break Ok(block);
+                }
+                Some(_) => {
+                    if let Some(stmt) = self.recovering_statement() {
+                        block.push(stmt);
+                    }
+                }
+                None => {
+                    break Err(Diagnostic::parse(
+                        "E0103",
+                        open,
+                        "unclosed block, expected `}` before end of file",
+                    ))
+                }
+            }
+        };
+        self.depth -= 1;
+        self.scopes.pop();
+        result
+    }
+
+    fn statement(&mut self) -> Result<Option<Stmt>> {
+        let doc = match self.doc_comment() {
+            Some((text, _)) if self.at_declaration() => Some(text),
+            Some((_, span)) => {
+                self.errors.push(Diagnostic::parse(
+                    "E0116",
+                    span,
+                    "doc comment must be followed by a declaration",
+                ));
+                if matches!(self.peek(), None | Some(TokenKind::CloseBrace)) {
+                    return Ok(None);
+                }
+                None
+            }
+            None => None,
+        };
+        let Some(tok) = self.next() else {
+            return Ok(None);
+        };
+        let start = self.prev;
+        let kind = match tok {
+            TokenKind::Keyword(word) => match word.as_str() {
+                "int" | "float" | "bigint" | "string" | "bool" => {
+                    let typ = self.array_suffix(Type::from_keyword(&word).unwrap())?;
+                    self.declaration(typ, doc)?
+                }
+                "print" | "println" => {
+                    let args = self.print_args()?;
+                    self.expect(TokenKind::Semicolon)?;
+                    StmtKind::Print {
+                        args,
+                        newline: word == "println",
+                    }
+                }
+                "if" => match self.if_statement() {
+                    Ok(kind) => kind,
+                    Err(e) => {
+                        self.skip_if(e);
+                        return Ok(None);
+                    }
+                },
+                "else" => {
+                    return Err(Diagnostic::parse(
+                        "E0111",
+                        start,
+                        "else without a preceding if",
+                    ))
+                }
+                "while" => {
+                    let condition = self.expression().map_err(|e| self.skip_loop(e));
+                    let Ok(condition) = condition else {
+                        return Ok(None);
+                    };
+                    StmtKind::While(condition, self.loop_body()?)
+                }
+                "for" => {
+                    self.scopes.push(HashMap::new());
+                    let result = self.for_statement().map_err(|e| self.skip_loop(e));
+                    self.scopes.pop();
+                    let Ok(kind) = result else {
+                        return Ok(None);
+                    };
+                    kind
+                }
+                "break" | "continue" => {
+                    if self.loops == 0 {
+                        return Err(Diagnostic::parse(
+                            "E0112",
+                            start,
+                            format!("{} outside of a loop", word),
+                        ));
+                    }
+                    self.expect(TokenKind::Semicolon)?;
+                    if word == "break" {
+                        StmtKind::Break
+                    } else {
+                        StmtKind::Continue
+                    }
+                }
+                "fn" => {
+                    let span = start.to(self.peek_span());
+                    let header = if self.depth > 0 {
+                        Err(Diagnostic::parse(
+                            "E0108",
+                            start,
+                            "functions can only be declared at the top level",
+                        ))
+                    } else {
+                        self.function_header()
+                    };
+                    let Ok(header) = header.map_err(|e| self.skip_function(e)) else {
+                        return Ok(None);
+                    };
+                    self.function(span, header, doc)?
+                }
+                "return" => {
+                    let Some(ret) = self.returns.clone() else {
+                        return Err(Diagnostic::parse(
+                            "E0107",
+                            start,
+                            "return outside of a function",
+                        ));
+                    };
+                    let value = match ret {
+                        Some(typ) => Some(self.expr_for(&typ)?),
+                        None => None,
+                    };
+                    self.expect(TokenKind::Semicolon)?;
+                    StmtKind::Return(value)
+                }
+                _ => return self.unexpected("statement", TokenKind::Keyword(word)),
+            },
+            TokenKind::Identifier(id) => {
+                let kind = self.simple_statement(id, start)?;
+                self.expect(TokenKind::Semicolon)?;
+                kind
+            }
+            TokenKind::Semicolon => return Ok(None),
+            t => return self.unexpected("statement", t),
+        };
+        Ok(Some(Stmt {
+            kind,
+            span: start.to(self.prev),
+        }))
+    }
+
+    /// Consumes any `///` lines before a statement, returning their text
+    /// joined by newlines and their span.
+    fn doc_comment(&mut self) -> Option<(String, Span)> {
+        let mut doc: Option<(String, Span)> = None;
+        while let Some(TokenKind::DocComment(line)) = self.peek() {
+            let line = line.clone();
+            self.next();
+            doc = Some(match doc {
+                Some((text, span)) => (text + "\n" + &line, span.to(self.prev)),
+                None => (line, self.prev),
+            });
+        }
+        doc
+    }
+
+    /// Parses an assignment or call statement starting with the identifier
+    /// `id`, without the trailing `;`.
+    fn simple_statement(&mut self, id: String, start: Span) -> Result<StmtKind> {
+        if self.peek() == Some(&TokenKind::OpenParen) {
+            return Ok(StmtKind::Expr(self.call(id, start)?));
+        }
+        let Some(typ) = self.lookup(&id) else {
+            return Err(Diagnostic::parse(
+                "E0104",
+                start,
+                format!("assignment to undeclared variable {}", id),
+            ));
+        };
+        if self.peek() == Some(&TokenKind::OpenBracket) {
+            let target = self.postfix(Expr::new(ExprKind::Variable(id), start))?;
+            let elem = self.type_of(&target);
+            let ExprKind::Index(array, index) = target.kind else {
+                unreachable!("postfix of '[' is an index expression")
+            };
+            self.expect(TokenKind::Assign)?;
+            let value = match elem {
+                Some(elem) => self.expr_for(&elem)?,
+                None => self.expression()?,
+            };
+            return Ok(StmtKind::SetIndex {
+                array: *array,
+                index: *index,
+                value,
+            });
+        }
+        self.expect(TokenKind::Assign)?;
+        let value = self.expr_for(&typ)?;
+        Ok(StmtKind::Assign(id, value))
+    }
+
+    fn loop_body(&mut self) -> Result<Block> {
+        self.loops += 1;
+        let body = self.block();
+        self.loops -= 1;
+        body
+    }
+
+    /// Parses either loop form after the `for` keyword. The caller provides
+    /// the scope holding the loop variable.
+    fn for_statement(&mut self) -> Result<StmtKind> {
+        if let Some(TokenKind::Identifier(_)) = self.peek() {
+            let var = self.identifier()?;
+            if !self.at_keyword("in") {
+                let found = self.advance("`in`")?;
+                return self.unexpected("`in`", found);
+            }
+            self.next();
+            // As with a declaration, the variable is in scope for the body
+            // even if the bounds fail to parse.
+            let bounds = self.range_bounds();
+            self.declare(&var, Type::Int);
+            let (start, end) = bounds?;
+            return Ok(StmtKind::ForRange {
+                var,
+                start,
+                end,
+                body: self.loop_body()?,
+            });
+        }
+
+        self.expect(TokenKind::OpenParen)?;
+        let init = if self.peek() == Some(&TokenKind::Semicolon) {
+            self.next();
+            None
+        } else {
+            let start = self.peek_span();
+            let kind = match self.advance("loop initializer")? {
+                TokenKind::Keyword(k) if Type::from_keyword(&k).is_some() => {
+                    let typ = self.array_suffix(Type::from_keyword(&k).unwrap())?;
+                    self.declaration(typ, None)?
+                }
+                TokenKind::Identifier(id) => {
+                    let kind = self.simple_statement(id, start)?;
+                    self.expect(TokenKind::Semicolon)?;
+                    kind
+                }
+                t => return self.unexpected("loop initializer", t),
+            };
+            Some(Box::new(Stmt {
+                kind,
+                span: start.to(self.prev),
+            }))
+        };
+        let condition = if self.peek() == Some(&TokenKind::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.expect(TokenKind::Semicolon)?;
+        let step = if self.peek() == Some(&TokenKind::CloseParen) {
+            None
+        } else {
+            let id = self.identifier()?;
+            let start = self.prev;
+            let kind = self.simple_statement(id, start)?;
+            Some(Box::new(Stmt {
+                kind,
+                span: start.to(self.prev),
+            }))
+        };
+        self.expect(TokenKind::CloseParen)?;
+        Ok(StmtKind::For {
+            init,
+            condition,
+            step,
+            body: self.loop_body()?,
+        })
+    }
+
+    /// Parses the `start..end` of a range loop.
+    fn range_bounds(&mut self) -> Result<(Expr, Expr)> {
+        let start = self.expression()?;
+        self.expect(TokenKind::DotDot)?;
+        Ok((start, self.expression()?))
+    }
+
+    /// Parses an `if` after its keyword, including any `else if` / `else`
+    /// chain. An `else if` becomes an else block holding the nested `if`.
+    fn if_statement(&mut self) -> Result<StmtKind> {
+        let condition = self.expression()?;
+        let then_block = self.block()?;
+        if !self.at_keyword("else") {
+            return Ok(StmtKind::If(condition, then_block, None));
+        }
+        self.next();
+        let else_block = if self.at_keyword("if") {
+            self.next();
+            let start = self.prev;
+            let kind = self.if_statement()?;
+            vec![Stmt {
+                kind,
+                span: start.to(self.prev),
+            }]
+        } else {
+            self.block()?
+        };
+        Ok(StmtKind::If(condition, then_block, Some(else_block)))
+    }
+
+    fn declaration(&mut self, typ: Type, doc: Option<String>) -> Result<StmtKind> {
+        let id = self.identifier()?;
+        self.expect(TokenKind::Assign)?;
+        // The name only comes into scope after its initializer, but is
+        // declared even if the initializer fails so later uses still parse.
+        let value = self.expr_for(&typ);
+        self.declare(&id, typ.clone());
+        let value = value?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(StmtKind::Declare {
+            typ,
+            name: id,
+            value,
+            doc,
+        })
+    }
+
+    /// Parses the body of a function whose header has been parsed. `span`
+    /// covers its `fn` keyword and name.
+    fn function(
+        &mut self,
+        span: Span,
+        (name, params, ret): Header,
+        doc: Option<String>,
+    ) -> Result<StmtKind> {
+        self.functions
+            .entry(name.clone())
+            .or_insert_with(|| Signature {
+                params: params.iter().map(|p| p.typ.clone()).collect(),
+                ret: ret.clone(),
+            });
+
+        self.scopes.push(HashMap::new());
+        for param in &params {
+            self.declare(&param.name, param.typ.clone());
+        }
+        self.returns = Some(ret.clone());
+        let body = self.block();
+        self.returns = None;
+        self.scopes.pop();
+        if builtins::is_builtin(&name) {
+            return Err(Diagnostic::parse(
+                "E0113",
+                span,
+                format!("{} is a built-in function and cannot be redefined", name),
+            ));
+        }
+
+        Ok(StmtKind::Function(Rc::new(Function {
+            name,
+            params,
+            ret,
+            body: body?,
+            span,
+            doc,
+        })))
+    }
+
+    /// Parses `name(type param, ...) -> type` after the `fn` keyword.
+    fn function_header(&mut self) -> Result<Header> {
+        let name = self.identifier()?;
+        self.expect(TokenKind::OpenParen)?;
+        let mut params = Vec::new();
+        if self.peek() == Some(&TokenKind::CloseParen) {
+            self.next();
+        } else {
+            loop {
+                let typ = self.type_name()?;
+                params.push(Param {
+                    typ,
+                    name: self.identifier()?,
+                });
+                match self.advance("`)`")? {
+                    TokenKind::Comma => {}
+                    TokenKind::CloseParen => break,
+                    t => return self.unexpected("`,` or `)`", t),
+                }
+            }
+        }
+        let ret = if self.peek() == Some(&TokenKind::Arrow) {
+            self.next();
+            Some(self.type_name()?)
+        } else {
+            None
+        };
+        Ok((name, params, ret))
+    }
+
+    /// Parses the argument list of a call to `name`, whose name has already
+    /// been consumed. The number and types of the arguments are left to the
+    /// checker; the parameter types only serve as hints.
+    fn call(&mut self, name: String, span: Span) -> Result<Expr> {
+        let signature = if builtins::is_builtin(&name) || self.broken_functions.contains(&name) {
+            None
+        } else {
+            match self.functions.get(&name) {
+                Some(signature) => Some(signature.clone()),
+                None => {
+                    return Err(Diagnostic::parse(
+                        "E0105",
+                        span,
+                        format!("call to undeclared function {}", name),
+                    ))
+                }
+            }
+        };
+        self.expect(TokenKind::OpenParen)?;
+        let mut args = Vec::new();
+        while self.peek() != Some(&TokenKind::CloseParen) {
+            if !args.is_empty() {
+                self.expect(TokenKind::Comma)?;
+            }
+            let hint = match &signature {
+                Some(signature) => signature.params.get(args.len()).cloned(),
+                // The value pushed onto an array has the array's element type.
+                None if name == "push" && args.len() == 1 => match self.type_of(&args[0]) {
+                    Some(Type::Array(elem)) => Some(*elem),
+                    _ => None,
+                },
+                None if name == "join" && args.is_empty() => {
+                    Some(Type::Array(Box::new(Type::String)))
+                }
+                None => None,
+            };
+            args.push(match hint {
+                Some(typ) => self.expr_for(&typ)?,
+                None => self.expression()?,
+            });
+        }
+        self.next();
+        Ok(Expr::new(ExprKind::Call(name, args), span))
+    }
+
+    /// Parses an expression with no expected type.
+    fn expression(&mut self) -> Result<Expr> {
+        let outer = self.hint.take();
+        let expr = self.binary_expr(0);
+        self.hint = outer;
+        expr
+    }
+    /// Parses an expression whose context expects `typ`. The type is not
+    /// enforced here, but gives array literals their element types.
+    fn expr_for(&mut self, typ: &Type) -> Result<Expr> {
+        let outer = self.hint.replace(typ.clone());
+        let expr = self.binary_expr(0);
+        self.hint = outer;
+        expr
+    }
+
+    /// Precedence climbing: parses a unary expression followed by any binary
+    /// operators that bind at least as tightly as `min`.
+    fn binary_expr(&mut self, min: u8) -> Result<Expr> {
+        let mut lhs = self.unary_expr()?;
+        while let Some((op, precedence)) = self.peek().and_then(binary_op) {
+            if precedence < min {
+                break;
+            }
+            self.next();
+            let span = self.prev;
+            // Operands on the right bind tighter, making operators
+            // left-associative.
+            let rhs = self.binary_expr(precedence + 1)?;
+            lhs = Expr::new(ExprKind::Binary(Box::new(lhs), op, Box::new(rhs)), span);
+        }
+        Ok(lhs)
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr> {
+        let op = match self.peek() {
+            Some(TokenKind::Minus) => UnaryOp::Neg,
+            Some(TokenKind::Plus) => UnaryOp::Plus,
+            Some(TokenKind::Bang) => UnaryOp::Not,
+            _ => {
+                let primary = self.primary()?;
+                return self.postfix(primary);
+            }
+        };
+        self.next();
+        let span = self.prev;
+        let operand = self.unary_expr()?;
+        Ok(Expr::new(ExprKind::Unary(op, Box::new(operand)), span))
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let kind = match self.advance("expression")? {
+            TokenKind::Number(n) => ExprKind::Number(n),
+            TokenKind::Float(x) => ExprKind::Float(x),
+            TokenKind::BigInt(n) => ExprKind::BigInt(n),
+            TokenKind::String(s) => ExprKind::String(s),
+            TokenKind::Interpolated(parts) => {
+                let span = self.prev;
+                let mut exprs = Vec::new();
+                for part in parts {
+                    exprs.push(match part {
+                        StringPart::Text(s) => Expr::new(ExprKind::String(s), span),
+                        StringPart::Expr(tokens) => self.interpolation(tokens)?,
+                    });
+                }
+                ExprKind::Interpolated(exprs)
+            }
+            TokenKind::True => ExprKind::Bool(true),
+            TokenKind::False => ExprKind::Bool(false),
+            TokenKind::Identifier(id) if self.peek() == Some(&TokenKind::OpenParen) => {
+                let span = self.prev;
+                return self.call(id, span);
+            }
+            TokenKind::Identifier(id) => self.variable(id)?,
+            TokenKind::Keyword(k) if self.peek() == Some(&TokenKind::OpenParen) => {
+                let span = self.prev;
+                let typ = match Type::from_keyword(&k) {
+                    Some(typ) if typ.is_numeric() => typ,
+                    _ => return self.unexpected("expression", TokenKind::Keyword(k)),
+                };
+                self.next();
+                let operand = self.expression()?;
+                self.expect(TokenKind::CloseParen)?;
+                return Ok(Expr::new(ExprKind::Cast(typ, Box::new(operand)), span));
+            }
+            TokenKind::OpenParen => {
+                let expr = self.binary_expr(0)?;
+                self.expect(TokenKind::CloseParen)?;
+                return Ok(expr);
+            }
+            TokenKind::OpenBracket => return self.array_literal(),
+            t => return self.unexpected("expression", t),
+        };
+        Ok(Expr::new(kind, self.prev))
+    }
+
+    /// Parses the tokens of an expression interpolated into the string just
+    /// consumed, ending with its closing `}`. Names are resolved in the scope
+    /// the string appears in.
+    fn interpolation(&mut self, tokens: Vec<Token>) -> Result<Expr> {
+        let outer = (
+            std::mem::replace(&mut self.tokens, tokens),
+            self.pos,
+            self.prev,
+        );
+        self.pos = 0;
+        let expr = self.expression();
+        let end = expr.is_ok().then(|| self.expect(TokenKind::CloseBrace));
+        (self.tokens, self.pos, self.prev) = outer;
+        end.transpose()?;
+        expr
+    }
+
+    /// Parses an array literal after its `[`. The element type comes from
+    /// the context if it expects an array, and otherwise from the first
+    /// element.
+    fn array_literal(&mut self) -> Result<Expr> {
+        let start = self.prev;
+        // The hint is the type of the whole expression, so it says nothing
+        // about a literal that is indexed.
+        let elem = match &self.hint {
+            Some(Type::Array(elem)) if !self.literal_is_indexed() => Some(elem.as_ref().clone()),
+            _ => None,
+        };
+        let mut items = Vec::new();
+        while self.peek() != Some(&TokenKind::CloseBracket) {
+            if !items.is_empty() {
+                self.expect(TokenKind::Comma)?;
+            }
+            items.push(match &elem {
+                Some(elem) => self.expr_for(elem)?,
+                None => self.expression()?,
+            });
+        }
+        self.next();
+        let span = start.to(self.prev);
+        let Some(elem) = elem.or_else(|| self.type_of(items.first()?)) else {
+            return Err(Diagnostic::parse(
+                "E0115",
+                span,
+                "cannot infer the element type of this array literal",
+            ));
+        };
+        Ok(Expr::new(ExprKind::Array(elem, items), span))
+    }
+
+    /// Whether the array literal whose `[` was just consumed is followed by
+    /// an index, as in `[a, b][i]`.
+    fn literal_is_indexed(&self) -> bool {
+        let mut depth = 1;
+        for (i, token) in self.tokens.iter().enumerate().skip(self.pos) {
+            match token.kind {
+                TokenKind::OpenBracket => depth += 1,
+                TokenKind::CloseBracket => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let next = self.tokens.get(i + 1).map(|t| &t.kind);
+                        return next == Some(&TokenKind::OpenBracket);
+                    }
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Applies any `[index]` suffixes to `expr`. Each index expression is
+    /// located at its opening bracket.
+    fn postfix(&mut self, mut expr: Expr) -> Result<Expr> {
+        while self.peek() == Some(&TokenKind::OpenBracket) {
+            self.next();
+            let span = self.prev;
+            let index = self.expr_for(&Type::Int)?;
+            self.expect(TokenKind::CloseBracket)?;
+            expr = Expr::new(ExprKind::Index(Box::new(expr), Box::new(index)), span);
+        }
+        Ok(expr)
+    }
+
+    /// The static type of an already parsed expression, if it has one. Only
+    /// used for hints; the checker reports type errors.
+    fn type_of(&self, expr: &Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Number(_) => Some(Type::Int),
+            ExprKind::Float(_) => Some(Type::Float),
+            ExprKind::BigInt(_) => Some(Type::BigInt),
+            ExprKind::String(_) => Some(Type::String),
+            ExprKind::Bool(_) => Some(Type::Bool),
+            ExprKind::Variable(id) => self.lookup(id),
+            ExprKind::Array(elem, _) => Some(Type::Array(Box::new(elem.clone()))),
+            ExprKind::Index(array, _) => match self.type_of(array)? {
+                Type::Array(elem) => Some(*elem),
+                _ => None,
+            },
+            ExprKind::Unary(op, operand) => op.result_type(&self.type_of(operand)?),
+            ExprKind::Binary(lhs, op, rhs) => {
+                op.result_type(&self.type_of(lhs)?, &self.type_of(rhs)?)
+            }
+            ExprKind::Call(name, args) if builtins::is_builtin(name) => {
+                let args: Option<Vec<_>> = args.iter().map(|a| self.type_of(a)).collect();
+                builtins::result_type(name, &args?).ok()?
+            }
+            ExprKind::Call(name, _) => self.functions.get(name)?.ret.clone(),
+            ExprKind::Cast(typ, _) => Some(typ.clone()),
+            ExprKind::Interpolated(_) => Some(Type::String),
+        }
+    }
+
+    /// Parses the parenthesized, comma-separated values of a `print`.
+    fn print_args(&mut self) -> Result<Vec<Expr>> {
+        self.expect(TokenKind::OpenParen)?;
+        let mut args = Vec::new();
+        while self.peek() != Some(&TokenKind::CloseParen) {
+            if !args.is_empty() {
+                self.expect(TokenKind::Comma)?;
+            }
+            args.push(self.expression()?);
+        }
+        self.next();
+        Ok(args)
+    }
+}
+
+/// The binary operator a token stands for and its precedence; higher binds
+/// tighter.
+fn binary_op(kind: &TokenKind) -> Option<(BinaryOp, u8)> {
+    Some(match kind {
+        TokenKind::Or => (BinaryOp::Or, 1),
+        TokenKind::And => (BinaryOp::And, 2),
+        TokenKind::EqualTo => (BinaryOp::Equal, 3),
+        TokenKind::NotEqual => (BinaryOp::NotEqual, 3),
+        TokenKind::LessThan => (BinaryOp::Less, 4),
+        TokenKind::LessThanEqual => (BinaryOp::LessEqual, 4),
+        TokenKind::GreaterThan => (BinaryOp::Greater, 4),
+        TokenKind::GreaterThanEqual => (BinaryOp::GreaterEqual, 4),
+        TokenKind::Plus => (BinaryOp::Add, 5),
+        TokenKind::Minus => (BinaryOp::Sub, 5),
+        TokenKind::Asterisk => (BinaryOp::Mul, 6),
+        TokenKind::Slash => (BinaryOp::Div, 6),
+        TokenKind::Percent => (BinaryOp::Rem, 6),
+        _ => return None,
+    })
+}
+
+/// Whether `keyword` starts a statement, leaving out the type keywords that
+/// start declarations but also appear inside other statements.
+fn starts_statement(keyword: &str) -> bool {
+    matches!(
+        keyword,
+        "print" | "println" | "if" | "while" | "for" | "break" | "continue" | "fn" | "return"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+
+    fn error_codes(source: &str) -> Vec<&'static str> {
+        match Parser::new(lex(source).unwrap()).parse() {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|e| e.code).collect(),
+        }
+    }
+
+    #[test]
+    fn recovers_inside_a_broken_loop_header() {
+        let source = "for (int i = 0; i < 3; i += 1) { if (i == 1) { break; } println(i); }";
+        assert_eq!(error_codes(source), ["E0101"]);
+        assert_eq!(error_codes("while 1 < { break; }"), ["E0101"]);
+        assert_eq!(
+            error_codes("for i in 0..x { println(i); break; }"),
+            ["E0109"]
+        );
+    }
+
+    #[test]
+    fn skips_a_function_with_a_broken_header() {
+        let source = "fn f(int a -> int { return a; }\nint z = 2;\nz = f(z);";
+        assert_eq!(error_codes(source), ["E0101"]);
+        let source = "if (true) { fn g(int a) { return; } }\nprintln(2);";
+        assert_eq!(error_codes(source), ["E0108"]);
+    }
+
+    #[test]
+    fn recovers_at_a_declaration_after_a_missing_semicolon() {
+        assert_eq!(
+            error_codes("int x = 5\nint y = 6;\nprintln(x + y);"),
+            ["E0101"]
+        );
+    }
+
+    #[test]
+    fn reports_errors_in_a_block_reached_while_recovering() {
+        assert_eq!(error_codes("if 1 + { println(y); }"), ["E0101", "E0109"]);
+    }
+
+    #[test]
+    fn shows_tokens_as_source_text() {
+        let message = |source: &str| match Parser::new(lex(source).unwrap()).parse() {
+            Ok(_) => String::new(),
+            Err(errors) => errors[0].message.clone(),
+        };
+        assert_eq!(message("int x + 1;"), "expected `=`, found `+`");
+        assert_eq!(message("for i 0..3 {}"), "expected `in`, found `0`");
+        assert_eq!(message("int x = in;"), "expected expression, found `in`");
+    }
+
+    #[test]
+    fn skips_the_else_chain_of_a_broken_if() {
+        let source = "int x = 1;\nif x > { println(1); } else { println(2); }";
+        assert_eq!(error_codes(source), ["E0101"]);
+        let source = "int x = 1;\nif x > 0 {} else if x < {} else if x == 2 {} else { x = ; }";
+        assert_eq!(error_codes(source), ["E0101", "E0101"]);
+    }
+}
@@ -0,0 +1,142 @@
+//! The deterministic pseudo-random number generator behind the random
+//! built-ins: xoshiro256** with its state filled in by splitmix64, so any
+//! 64-bit seed gives a good generator and the same seed always gives the
+//! same sequence.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let mut x = seed;
+        let mut splitmix = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let z = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Rng {
+            state: [splitmix(), splitmix(), splitmix(), splitmix()],
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// A uniformly distributed number in `0..n`, or in the whole `u64`
+    /// range if `n` is zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return self.next_u64();
+        }
+        // Numbers under 2^64 % n would make the low results more likely.
+        let skip = n.wrapping_neg() % n;
+        loop {
+            let x = self.next_u64();
+            if x >= skip {
+                return x % n;
+            }
+        }
+    }
+
+    /// A uniformly distributed int in `low..=high`, where `low <= high`.
+    pub fn int(&mut self, low: i64, high: i64) -> i64 {
+        let size = (high.wrapping_sub(low) as u64).wrapping_add(1);
+        low.wrapping_add(self.below(size) as i64)
+    }
+
+    /// A uniformly distributed float in `0.0..1.0`.
+    pub fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Seeded differently on every run.
+impl Default for Rng {
+    fn default() -> Rng {
+        Rng::new(RandomState::new().build_hasher().finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seed_gives_a_fixed_sequence() {
+        // Checked against the reference splitmix64 and xoshiro256**.
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.next_u64(), 0x1578_0b2e_0c2e_c716);
+        assert_eq!(rng.next_u64(), 0x6104_d986_6d11_3a7e);
+        assert_eq!(rng.next_u64(), 0xae17_5332_39e4_99a1);
+
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        for _ in 0..100 {
+            assert_eq!(a.int(-10, 10), b.int(-10, 10));
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn ints_cover_the_whole_range_inclusively() {
+        let mut rng = Rng::new(1);
+        assert_eq!(rng.int(5, 5), 5);
+        assert_eq!(rng.int(i64::MAX, i64::MAX), i64::MAX);
+        let (mut low, mut high) = (false, false);
+        for _ in 0..1000 {
+            let n = rng.int(-2, 2);
+            assert!((-2..=2).contains(&n));
+            low |= n == -2;
+            high |= n == 2;
+        }
+        assert!(low && high);
+        let mut negative = false;
+        for _ in 0..100 {
+            negative |= rng.int(i64::MIN, i64::MAX) < 0;
+        }
+        assert!(negative);
+    }
+
+    #[test]
+    fn below_is_unbiased() {
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..30_000 {
+            counts[rng.below(3) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&n| (9_500..10_500).contains(&n)),
+            "{:?}",
+            counts
+        );
+
+        // Reducing every number modulo n without rejecting any would put
+        // 5/8 of the results in the lower half of this range.
+        let n = (1 << 63) + (1 << 62);
+        let lower = (0..10_000).filter(|_| rng.below(n) < n / 2).count();
+        assert!((4_700..5_300).contains(&lower), "{}", lower);
+    }
+
+    #[test]
+    fn floats_are_in_the_unit_interval() {
+        let mut rng = Rng::new(4);
+        for _ in 0..1000 {
+            let x = rng.float();
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+}
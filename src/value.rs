@@ -0,0 +1,230 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::{BinaryOp, Type, UnaryOp};
+use crate::bigint::BigInt;
+
+/// A runtime value of any `.oxi` type.
+#[derive(PartialEq, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    BigInt(BigInt),
+    String(String),
+    Bool(bool),
+    /// Arrays are shared: assigning or passing one refers to the same
+    /// elements.
+    Array(Rc<Array>),
+}
+
+#[derive(PartialEq)]
+pub struct Array {
+    pub elem: Type,
+    pub items: RefCell<Vec<Value>>,
+}
+
+impl Value {
+    pub fn array(elem: Type, items: Vec<Value>) -> Value {
+        Value::Array(Rc::new(Array {
+            elem,
+            items: RefCell::new(items),
+        }))
+    }
+
+    pub fn typ(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::BigInt(_) => Type::BigInt,
+            Value::String(_) => Type::String,
+            Value::Bool(_) => Type::Bool,
+            Value::Array(array) => Type::Array(Box::new(array.elem.clone())),
+        }
+    }
+
+    /// The value of an int or float as a float.
+    fn float(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// The value of an int or bigint as a bigint.
+    fn big(&self) -> Option<BigInt> {
+        match self {
+            Value::Int(n) => Some(BigInt::from(*n)),
+            Value::BigInt(n) => Some(n.clone()),
+            _ => None,
+        }
+    }
+
+    /// Converts a number to the numeric type `typ`. Floats are truncated
+    /// toward zero. `None` if the value is out of range for `typ` or is a
+    /// float that isn't finite.
+    pub fn cast(&self, typ: &Type) -> Option<Value> {
+        use Value::*;
+        match (self, typ) {
+            (Int(_), Type::Int) | (Float(_), Type::Float) | (BigInt(_), Type::BigInt) => {
+                Some(self.clone())
+            }
+            (Int(n), Type::Float) => Some(Float(*n as f64)),
+            (Int(n), Type::BigInt) => Some(BigInt((*n).into())),
+            (BigInt(n), Type::Int) => n.to_i64().map(Int),
+            (BigInt(n), Type::Float) => {
+                let x: f64 = n.to_string().parse().ok()?;
+                x.is_finite().then_some(Float(x))
+            }
+            // The range is written with 2^63, which unlike i64::MAX is exact
+            // as a float.
+            (Float(x), Type::Int) if (-2f64.powi(63)..2f64.powi(63)).contains(&x.trunc()) => {
+                Some(Int(x.trunc() as i64))
+            }
+            (Float(x), Type::BigInt) if x.is_finite() => {
+                crate::bigint::BigInt::parse(&format!("{:.0}", x.trunc())).map(BigInt)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn unary(&self, op: UnaryOp) -> Result<Value, OpError> {
+        use Value::*;
+        match (op, self) {
+            (UnaryOp::Neg, Int(n)) => n.checked_neg().map(Int).ok_or(OpError::Overflow),
+            (UnaryOp::Neg, Float(x)) => Ok(Float(-x)),
+            (UnaryOp::Neg, BigInt(n)) => Ok(BigInt(-n)),
+            (UnaryOp::Plus, Int(_) | Float(_) | BigInt(_)) => Ok(self.clone()),
+            (UnaryOp::Not, Bool(b)) => Ok(Bool(!b)),
+            _ => Err(OpError::Invalid),
+        }
+    }
+
+    /// Applies `op` to `self` and `rhs`. Mixed numeric operands are promoted
+    /// as described by `Type::promote`: arithmetic on two ints is checked
+    /// for overflow, an int with a bigint is exact, and an int with a float
+    /// is done in floating point.
+    ///
+    /// `/` on ints and bigints truncates toward zero and `%` takes the sign
+    /// of the dividend, so `a == a / b * b + a % b`. Dividing by zero is an
+    /// error for every numeric type, floats included.
+    pub fn binary(&self, op: BinaryOp, rhs: &Value) -> Result<Value, OpError> {
+        use Value::*;
+        match (op, self, rhs) {
+            (BinaryOp::Add, String(_), _) | (BinaryOp::Add, _, String(_)) => {
+                Ok(String(format!("{}{}", self, rhs)))
+            }
+            (BinaryOp::And, Bool(a), Bool(b)) => Ok(Bool(*a && *b)),
+            (BinaryOp::Or, Bool(a), Bool(b)) => Ok(Bool(*a || *b)),
+            (BinaryOp::Equal, String(a), String(b)) => Ok(Bool(a == b)),
+            (BinaryOp::NotEqual, String(a), String(b)) => Ok(Bool(a != b)),
+            (BinaryOp::Equal, Bool(a), Bool(b)) => Ok(Bool(a == b)),
+            (BinaryOp::NotEqual, Bool(a), Bool(b)) => Ok(Bool(a != b)),
+            (_, Int(a), Int(b)) => int_op(op, *a, *b),
+            (_, Int(_) | BigInt(_), Int(_) | BigInt(_)) => {
+                big_op(op, &self.big().unwrap(), &rhs.big().unwrap())
+            }
+            (_, Int(_) | Float(_), Int(_) | Float(_)) => {
+                float_op(op, self.float().unwrap(), rhs.float().unwrap())
+            }
+            _ => Err(OpError::Invalid),
+        }
+    }
+}
+
+/// Why a binary operator could not be applied.
+#[derive(Debug, PartialEq)]
+pub enum OpError {
+    /// The operator is not defined for the operand types.
+    Invalid,
+    /// The result does not fit in an `int`.
+    Overflow,
+    DivisionByZero,
+}
+
+fn int_op(op: BinaryOp, a: i64, b: i64) -> Result<Value, OpError> {
+    let result = match op {
+        BinaryOp::Add => a.checked_add(b),
+        BinaryOp::Sub => a.checked_sub(b),
+        BinaryOp::Mul => a.checked_mul(b),
+        BinaryOp::Div | BinaryOp::Rem if b == 0 => return Err(OpError::DivisionByZero),
+        BinaryOp::Div => a.checked_div(b),
+        BinaryOp::Rem => a.checked_rem(b),
+        _ => return compare(op, Some(a.cmp(&b))),
+    };
+    result.map(Value::Int).ok_or(OpError::Overflow)
+}
+
+fn big_op(op: BinaryOp, a: &BigInt, b: &BigInt) -> Result<Value, OpError> {
+    Ok(Value::BigInt(match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div => a.div_rem(b).ok_or(OpError::DivisionByZero)?.0,
+        BinaryOp::Rem => a.div_rem(b).ok_or(OpError::DivisionByZero)?.1,
+        _ => return compare(op, Some(a.cmp(b))),
+    }))
+}
+
+fn float_op(op: BinaryOp, a: f64, b: f64) -> Result<Value, OpError> {
+    Ok(Value::Float(match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div | BinaryOp::Rem if b == 0.0 => return Err(OpError::DivisionByZero),
+        BinaryOp::Div => a / b,
+        BinaryOp::Rem => a % b,
+        _ => return compare(op, a.partial_cmp(&b)),
+    }))
+}
+
+/// Applies the comparison `op` to the ordering of its operands. Unordered
+/// operands (a NaN) compare false, except with `!=`.
+fn compare(op: BinaryOp, ordering: Option<Ordering>) -> Result<Value, OpError> {
+    let test = match op {
+        BinaryOp::NotEqual => return Ok(Value::Bool(!ordering.is_some_and(Ordering::is_eq))),
+        BinaryOp::Equal => Ordering::is_eq,
+        BinaryOp::Less => Ordering::is_lt,
+        BinaryOp::LessEqual => Ordering::is_le,
+        BinaryOp::Greater => Ordering::is_gt,
+        BinaryOp::GreaterEqual => Ordering::is_ge,
+        _ => return Err(OpError::Invalid),
+    };
+    Ok(Value::Bool(ordering.is_some_and(test)))
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            // Debug formatting keeps the `.0` on whole numbers so floats
+            // never print like ints.
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::BigInt(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Array(array) => {
+                write!(f, "[")?;
+                for (i, item) in array.items.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+/// Formats the value as it would be written in source, quoting strings.
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::BigInt(n) => write!(f, "{}n", n),
+            v => write!(f, "{}", v),
+        }
+    }
+}